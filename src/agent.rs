@@ -0,0 +1,149 @@
+use crate::{
+    clock::SharedClock,
+    series::{SampleCursor, Series},
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::{eyre, Report, WrapErr};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+pub struct AgentConfig {
+    // host:port the agent listens on
+    pub addr: String,
+    // Prefix for the agent's targets, the name it reports itself when unset
+    pub name: Option<String>,
+}
+
+// First line a viewer sends after connecting
+#[derive(Serialize, Deserialize)]
+pub struct ViewerHello {
+    // Unix time of the newest sample the viewer already has, everything kept is sent when unset
+    pub since: Option<f64>,
+}
+
+// Lines an agent sends, times are Unix time since the two sides don't share a start time
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentMessage {
+    Hello {
+        agent: String,
+    },
+    Sample {
+        target: String,
+        time: f64,
+        rtt: Option<f64>,
+    },
+    Heartbeat,
+    // Sent by newer agents, viewers skip what they don't know
+    #[serde(other)]
+    Unknown,
+}
+
+impl AgentMessage {
+    fn to_line(&self) -> String {
+        let mut line = serde_json::to_string(self).unwrap_or_default();
+        line.push('\n');
+        line
+    }
+}
+
+async fn serve_viewer(
+    stream: TcpStream,
+    name: &str,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    clock: SharedClock,
+) -> Result<(), Report> {
+    let (read, mut write) = stream.into_split();
+
+    let hello = tokio::time::timeout(HELLO_TIMEOUT, BufReader::new(read).lines().next_line())
+        .await
+        .map_err(|_| eyre!("No hello received"))??
+        .ok_or_else(|| eyre!("Connection closed before hello"))?;
+    let hello: ViewerHello = serde_json::from_str(&hello).wrap_err("Invalid hello")?;
+
+    let mut cursor = match hello.since {
+        Some(since) => SampleCursor::starting_at(clock.elapsed_at_unix_time(since)),
+        None => SampleCursor::default(),
+    };
+
+    write
+        .write_all(
+            AgentMessage::Hello {
+                agent: name.to_string(),
+            }
+            .to_line()
+            .as_bytes(),
+        )
+        .await?;
+
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last_sent = tokio::time::Instant::now();
+
+    loop {
+        interval.tick().await;
+
+        let mut lines = String::new();
+
+        if let Ok(ts_hash) = timeseries_hashref.lock() {
+            for (target, series) in ts_hash.iter() {
+                for sample in cursor.read_new(target, series) {
+                    lines += &AgentMessage::Sample {
+                        target: target.clone(),
+                        time: clock.unix_time_at(sample.time),
+                        rtt: sample.rtt,
+                    }
+                    .to_line();
+                }
+            }
+            cursor.retain(|target| ts_hash.contains_key(target));
+        }
+
+        // Lets the viewer tell a quiet agent from a dead connection
+        if lines.is_empty() && last_sent.elapsed() >= HEARTBEAT_INTERVAL {
+            lines = AgentMessage::Heartbeat.to_line();
+        }
+
+        if !lines.is_empty() {
+            write.write_all(lines.as_bytes()).await?;
+            last_sent = tokio::time::Instant::now();
+        }
+    }
+}
+
+pub async fn serve(
+    listener: TcpListener,
+    name: String,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    clock: SharedClock,
+) -> Result<(), Report> {
+    let name = Arc::new(name);
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let name = name.clone();
+        let timeseries_hashref = timeseries_hashref.clone();
+        let clock = clock.clone();
+
+        tokio::spawn(async move {
+            tracing::info!("Viewer {peer} connected");
+
+            if let Err(e) = serve_viewer(stream, &name, timeseries_hashref, clock).await {
+                tracing::info!("Viewer {peer} disconnected: {e}");
+            }
+        });
+    }
+}
@@ -0,0 +1,31 @@
+use crate::init::AgentArgs;
+
+use color_eyre::eyre::{eyre, Report, WrapErr};
+use egui_ping_graph::ProbeRuntime;
+use tokio::net::TcpListener;
+
+// Runs the probes without a window and streams their samples to viewers until interrupted
+pub fn run(probes: &ProbeRuntime, args: &AgentArgs) -> Result<(), Report> {
+    let name = args
+        .name
+        .clone()
+        .or_else(|| gethostname::gethostname().into_string().ok())
+        .filter(|x| !x.trim().is_empty())
+        .ok_or_else(|| eyre!("Couldn't look up the host name, pass --name"))?;
+
+    for target in &args.targets {
+        probes.add_target(target);
+    }
+
+    let listener = probes
+        .block_on(TcpListener::bind(args.listen))
+        .wrap_err_with(|| format!("Failed to listen on {}", args.listen))?;
+    tracing::info!("Agent {name} serving samples on {}", args.listen);
+
+    probes.block_on(async {
+        tokio::select! {
+            result = probes.serve_viewers(listener, name) => result,
+            _ = tokio::signal::ctrl_c() => Ok(()),
+        }
+    })
+}
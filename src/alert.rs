@@ -0,0 +1,460 @@
+use crate::{funcs, series::Series};
+
+use std::collections::HashMap;
+
+use color_eyre::eyre::{bail, Report};
+use serde::Deserialize;
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertMetric {
+    Latency,
+    Jitter,
+    Loss,
+}
+
+impl AlertMetric {
+    pub const ALL: [AlertMetric; 3] = [Self::Latency, Self::Jitter, Self::Loss];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Latency => "latency",
+            Self::Jitter => "jitter",
+            Self::Loss => "loss",
+        }
+    }
+
+    pub fn fmt_value(&self, val: f64) -> String {
+        match self {
+            Self::Latency | Self::Jitter => funcs::fmt_float_s(val),
+            Self::Loss => format!("{:.1}%", val * 100.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct AlertRule {
+    pub name: String,
+    // None applies the rule to every target
+    pub target: Option<String>,
+    pub metric: AlertMetric,
+    pub comparison: Comparison,
+    // Seconds for latency and jitter, fraction for loss
+    pub threshold: f64,
+    // Samples younger than this are aggregated into the evaluated value
+    pub window_secs: f64,
+    // How long the condition has to hold before the alert fires
+    pub for_secs: f64,
+    // A firing alert only resolves once the value is this fraction past the threshold
+    pub hysteresis: f64,
+    // Notification sinks to route this rule to, empty routes to all of them
+    pub sinks: Vec<String>,
+}
+
+impl Default for AlertRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            target: None,
+            metric: AlertMetric::Latency,
+            comparison: Comparison::Above,
+            threshold: 0.1,
+            window_secs: 1.0,
+            for_secs: 10.0,
+            hysteresis: 0.1,
+            sinks: vec![],
+        }
+    }
+}
+
+impl AlertRule {
+    // States are keyed by rule name, and routing by sink name
+    pub fn validate(&self, sink_names: &[&str]) -> Result<(), Report> {
+        if self.name.is_empty() {
+            bail!("Alert rule `{}` has no name", self.describe());
+        }
+        if let Some(sink) = self
+            .sinks
+            .iter()
+            .find(|x| !sink_names.contains(&x.as_str()))
+        {
+            bail!("Alert rule {} routes to unknown sink {sink}", self.name);
+        }
+
+        Ok(())
+    }
+
+    pub fn applies_to(&self, target: &str) -> bool {
+        self.target.as_deref().is_none_or(|x| x == target)
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} to {} {} {} over {} for {}",
+            self.metric.label(),
+            self.target.as_deref().unwrap_or("any"),
+            match self.comparison {
+                Comparison::Above => ">",
+                Comparison::Below => "<",
+            },
+            self.metric.fmt_value(self.threshold),
+            funcs::fmt_float_s(self.window_secs),
+            funcs::fmt_float_s(self.for_secs),
+        )
+    }
+
+    fn is_breached(&self, val: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => val > self.threshold,
+            Comparison::Below => val < self.threshold,
+        }
+    }
+
+    fn is_cleared(&self, val: f64) -> bool {
+        match self.comparison {
+            Comparison::Above => val < self.threshold * (1.0 - self.hysteresis),
+            Comparison::Below => val > self.threshold * (1.0 + self.hysteresis),
+        }
+    }
+
+    fn evaluate(&self, series: &Series, now: f64) -> Option<f64> {
+        let since = now - self.window_secs;
+        let samples: Vec<f64> = series
+            .get_younger_than(since)
+            .iter()
+            .filter_map(|x| x.map(|x| x.1))
+            .collect();
+
+        match self.metric {
+            AlertMetric::Latency if samples.is_empty() => None,
+            AlertMetric::Latency => Some(samples.iter().sum::<f64>() / samples.len() as f64),
+            AlertMetric::Jitter => {
+                use statrs::statistics::Statistics;
+                let std_dev = samples.iter().std_dev();
+                (!std_dev.is_nan()).then_some(std_dev)
+            }
+            AlertMetric::Loss => {
+                let lost = series.timeout_at.iter().filter(|&&x| x > since).count();
+                match samples.len() + lost {
+                    0 => None,
+                    total => Some(lost as f64 / total as f64),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlertState {
+    Inactive,
+    Pending { since: f64 },
+    Firing { since: f64 },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AlertTransition {
+    Pending,
+    Firing,
+    Resolved,
+}
+
+impl AlertTransition {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Firing => "firing",
+            Self::Resolved => "resolved",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AlertEvent {
+    pub time: f64,
+    pub rule: AlertRule,
+    pub target: String,
+    pub transition: AlertTransition,
+    pub value: Option<f64>,
+}
+
+impl AlertEvent {
+    pub fn describe(&self) -> String {
+        format!(
+            "alert {} for {} {} ({}, value {})",
+            self.rule.name,
+            self.target,
+            self.transition.label(),
+            self.rule.describe(),
+            self.value
+                .map_or("-".to_string(), |x| self.rule.metric.fmt_value(x)),
+        )
+    }
+}
+
+// Time span an alert was firing, end is None while it's still firing
+#[derive(Clone)]
+pub struct FiringInterval {
+    pub rule: String,
+    pub target: String,
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct AlertEngine {
+    pub rules: Vec<AlertRule>,
+    pub states: HashMap<(String, String), AlertState>,
+    pub intervals: Vec<FiringInterval>,
+    // Transitions not yet picked up by the notifier
+    pub outbox: Vec<AlertEvent>,
+}
+
+impl AlertEngine {
+    pub fn with_rules(rules: Vec<AlertRule>) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    pub fn remove_rule(&mut self, name: &str) {
+        self.rules.retain(|x| x.name != name);
+        self.states.retain(|(rule, _), _| rule != name);
+
+        for interval in self.intervals.iter_mut().filter(|x| x.rule == name) {
+            interval.end.get_or_insert(interval.start);
+        }
+    }
+
+    pub fn firing_for(&self, target: &str) -> Vec<&str> {
+        let mut rules: Vec<&str> = self
+            .states
+            .iter()
+            .filter(|((_, t), state)| t == target && matches!(state, AlertState::Firing { .. }))
+            .map(|((rule, _), _)| rule.as_str())
+            .collect();
+        rules.sort();
+        rules
+    }
+
+    fn transition(&mut self, rule: &AlertRule, target: &str, now: f64, value: Option<f64>) {
+        let key = (rule.name.clone(), target.to_string());
+        let state = *self.states.get(&key).unwrap_or(&AlertState::Inactive);
+
+        let next = match (state, value) {
+            (AlertState::Firing { .. }, Some(val)) if rule.is_cleared(val) => AlertState::Inactive,
+            (AlertState::Firing { .. }, _) => state,
+            (AlertState::Pending { since }, Some(val)) if rule.is_breached(val) => {
+                if now - since >= rule.for_secs {
+                    AlertState::Firing { since: now }
+                } else {
+                    state
+                }
+            }
+            (AlertState::Inactive, Some(val)) if rule.is_breached(val) => {
+                if rule.for_secs <= 0.0 {
+                    AlertState::Firing { since: now }
+                } else {
+                    AlertState::Pending { since: now }
+                }
+            }
+            _ => AlertState::Inactive,
+        };
+
+        if next == state {
+            return;
+        }
+
+        let change = match (state, next) {
+            (_, AlertState::Pending { .. }) => Some(AlertTransition::Pending),
+            (_, AlertState::Firing { .. }) => Some(AlertTransition::Firing),
+            (AlertState::Firing { .. }, AlertState::Inactive) => Some(AlertTransition::Resolved),
+            _ => None,
+        };
+
+        match change {
+            Some(AlertTransition::Firing) => self.intervals.push(FiringInterval {
+                rule: rule.name.clone(),
+                target: target.to_string(),
+                start: now,
+                end: None,
+            }),
+            Some(AlertTransition::Resolved) => {
+                if let Some(interval) = self
+                    .intervals
+                    .iter_mut()
+                    .rev()
+                    .find(|x| x.rule == rule.name && x.target == target && x.end.is_none())
+                {
+                    interval.end = Some(now);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(transition) = change {
+            let event = AlertEvent {
+                time: now,
+                rule: rule.clone(),
+                target: target.to_string(),
+                transition,
+                value,
+            };
+
+            tracing::warn!("{}", event.describe());
+            self.outbox.push(event);
+        }
+
+        self.states.insert(key, next);
+    }
+
+    pub fn evaluate(&mut self, timeseries: &HashMap<String, Series>, now: f64) {
+        // Removed targets can't resolve on their own, and would fire again as soon as a target
+        // of the same name is added back
+        self.states
+            .retain(|(_, target), _| timeseries.contains_key(target));
+        for interval in self.intervals.iter_mut() {
+            if !timeseries.contains_key(&interval.target) {
+                interval.end.get_or_insert(now);
+            }
+        }
+
+        for rule in self.rules.clone() {
+            for (target, series) in timeseries.iter().filter(|(t, _)| rule.applies_to(t)) {
+                let value = rule.evaluate(series, now);
+                self.transition(&rule, target, now, value);
+            }
+        }
+    }
+
+    pub fn remove_older_than(&mut self, time: f64) {
+        self.intervals
+            .retain(|x| x.end.is_none_or(|end| end > time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+
+    struct Harness {
+        clock: ManualClock,
+        engine: AlertEngine,
+        timeseries: HashMap<String, Series>,
+    }
+
+    impl Harness {
+        fn new(rule: AlertRule) -> Self {
+            Self {
+                clock: ManualClock::new(
+                    time::OffsetDateTime::from_unix_timestamp(1_704_067_200).unwrap(),
+                ),
+                engine: AlertEngine::with_rules(vec![rule]),
+                timeseries: HashMap::from([("host".to_string(), Series::default())]),
+            }
+        }
+
+        // One reply per second for `secs`, evaluating after each like the alerter does
+        fn reply_for(&mut self, secs: u32, rtt: f64) -> Vec<AlertTransition> {
+            let mut transitions = vec![];
+
+            for _ in 0..secs {
+                self.clock.advance(1.0);
+                let now = self.clock.elapsed();
+
+                if let Some(series) = self.timeseries.get_mut("host") {
+                    series.push(Some((now, rtt)));
+                }
+                self.engine.evaluate(&self.timeseries, now);
+
+                transitions.extend(self.engine.outbox.drain(..).map(|x| x.transition));
+            }
+
+            transitions
+        }
+
+        fn state(&self) -> AlertState {
+            let key = ("slow".to_string(), "host".to_string());
+            *self
+                .engine
+                .states
+                .get(&key)
+                .unwrap_or(&AlertState::Inactive)
+        }
+    }
+
+    fn latency_rule() -> AlertRule {
+        AlertRule {
+            name: "slow".to_string(),
+            threshold: 0.1,
+            window_secs: 1.0,
+            for_secs: 5.0,
+            hysteresis: 0.2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fires_only_after_for_secs() {
+        let mut harness = Harness::new(latency_rule());
+
+        assert!(harness.reply_for(3, 0.05).is_empty());
+        assert!(harness.state() == AlertState::Inactive);
+
+        assert!(harness.reply_for(1, 0.2) == [AlertTransition::Pending]);
+        assert!(harness.state() == AlertState::Pending { since: 4.0 });
+
+        assert!(harness.reply_for(4, 0.2).is_empty());
+        assert!(harness.reply_for(1, 0.2) == [AlertTransition::Firing]);
+        assert!(harness.state() == AlertState::Firing { since: 9.0 });
+        assert_eq!(harness.engine.firing_for("host"), ["slow"]);
+    }
+
+    #[test]
+    fn short_breach_never_fires() {
+        let mut harness = Harness::new(latency_rule());
+
+        assert!(harness.reply_for(3, 0.2) == [AlertTransition::Pending]);
+        assert!(harness.reply_for(10, 0.05).is_empty());
+        assert!(harness.state() == AlertState::Inactive);
+        assert!(harness.engine.intervals.is_empty());
+    }
+
+    #[test]
+    fn resolves_past_hysteresis() {
+        let mut harness = Harness::new(latency_rule());
+        harness.reply_for(6, 0.2);
+        assert!(harness.state() == AlertState::Firing { since: 6.0 });
+
+        // Below the threshold but within the hysteresis band keeps it firing
+        assert!(harness.reply_for(5, 0.09).is_empty());
+
+        assert!(harness.reply_for(1, 0.07) == [AlertTransition::Resolved]);
+        assert!(harness.state() == AlertState::Inactive);
+
+        let interval = &harness.engine.intervals[0];
+        assert_eq!((interval.start, interval.end), (6.0, Some(12.0)));
+    }
+
+    #[test]
+    fn removed_target_drops_its_state() {
+        let mut harness = Harness::new(latency_rule());
+        harness.reply_for(6, 0.2);
+        assert_eq!(harness.engine.firing_for("host"), ["slow"]);
+
+        harness.timeseries.clear();
+        harness.engine.evaluate(&harness.timeseries, 7.0);
+
+        assert!(harness.engine.states.is_empty());
+        assert_eq!(harness.engine.intervals[0].end, Some(7.0));
+    }
+}
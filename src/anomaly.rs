@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+
+use crate::{clock::Clock, funcs};
+
+const WARMUP_SAMPLES: usize = 20;
+const RECENT_CAP: usize = 64;
+
+// CUSUM slack and decision threshold, both in standard deviations
+const CUSUM_K: f64 = 0.5;
+const CUSUM_H: f64 = 8.0;
+// Clamp per-sample z-scores fed into CUSUM so a single spike can't fake a level shift
+const CUSUM_Z_CLAMP: f64 = 4.0;
+
+const SPIKE_Z: f64 = 6.0;
+const SPIKE_MIN_DELTA: f64 = 0.005;
+
+// Floors for the baseline deviation, so very stable links don't alarm on sub-ms jitter
+const MIN_STD_DEV: f64 = 0.0005;
+const MIN_STD_DEV_RATIO: f64 = 0.05;
+
+const BASELINE_ALPHA: f64 = 0.02;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AnomalyKind {
+    LevelShift { before: f64, after: f64 },
+    Spike { value: f64, baseline: f64 },
+}
+
+#[derive(Clone, Copy)]
+pub struct Anomaly {
+    pub time: f64,
+    pub kind: AnomalyKind,
+}
+
+impl Anomaly {
+    pub fn describe(&self, target: &str, clock: &dyn Clock) -> String {
+        match self.kind {
+            AnomalyKind::LevelShift { before, after } => format!(
+                "latency to {target} shifted from {} to {} at {}",
+                funcs::fmt_float_s(before),
+                funcs::fmt_float_s(after),
+                funcs::x_axis_fmt(clock, self.time)
+            ),
+            AnomalyKind::Spike { value, baseline } => format!(
+                "latency to {target} spiked to {} (baseline {}) at {}",
+                funcs::fmt_float_s(value),
+                funcs::fmt_float_s(baseline),
+                funcs::x_axis_fmt(clock, self.time)
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct AnomalyDetector {
+    mean: f64,
+    var: f64,
+    warmed_up: bool,
+
+    cusum_pos: f64,
+    cusum_neg: f64,
+    // Index into `recent` where the currently accumulating run started
+    run_pos_start: usize,
+    run_neg_start: usize,
+
+    recent: VecDeque<(f64, f64)>,
+}
+
+impl AnomalyDetector {
+    fn std_dev(&self) -> f64 {
+        self.var
+            .sqrt()
+            .max(MIN_STD_DEV)
+            .max(self.mean * MIN_STD_DEV_RATIO)
+    }
+
+    fn reset_baseline(&mut self, samples: Vec<(f64, f64)>) {
+        let data: Vec<f64> = samples.iter().map(|x| x.1).collect();
+        let mean = data.iter().sum::<f64>() / data.len().max(1) as f64;
+        let var = data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / data.len().max(1) as f64;
+
+        self.mean = mean;
+        self.var = var;
+        self.warmed_up = data.len() >= WARMUP_SAMPLES;
+        self.cusum_pos = 0.0;
+        self.cusum_neg = 0.0;
+        self.recent = samples.into();
+        self.run_pos_start = self.recent.len();
+        self.run_neg_start = self.recent.len();
+    }
+
+    fn push_recent(&mut self, sample: (f64, f64)) {
+        self.recent.push_back(sample);
+
+        if self.recent.len() > RECENT_CAP {
+            self.recent.pop_front();
+            self.run_pos_start = self.run_pos_start.saturating_sub(1);
+            self.run_neg_start = self.run_neg_start.saturating_sub(1);
+        }
+    }
+
+    fn level_shift(&mut self, run_start: usize) -> Anomaly {
+        let mut run: Vec<(f64, f64)> = self.recent.iter().skip(run_start).copied().collect();
+        let mean =
+            |run: &[(f64, f64)]| run.iter().map(|x| x.1).sum::<f64>() / run.len().max(1) as f64;
+
+        // The run can start on noise just before the shift, those samples belong to the old level
+        let after = mean(&run);
+        let noise = run
+            .iter()
+            .take_while(|x| (x.1 - self.mean).abs() < (x.1 - after).abs())
+            .count();
+        run.drain(..noise.min(run.len().saturating_sub(1)));
+        let after = mean(&run);
+
+        let anomaly = Anomaly {
+            time: run.first().map(|x| x.0).unwrap_or_default(),
+            kind: AnomalyKind::LevelShift {
+                before: self.mean,
+                after,
+            },
+        };
+
+        self.reset_baseline(run);
+        anomaly
+    }
+
+    pub fn push(&mut self, time: f64, value: f64) -> Option<Anomaly> {
+        if !self.warmed_up {
+            self.push_recent((time, value));
+
+            if self.recent.len() >= WARMUP_SAMPLES {
+                let samples = self.recent.iter().copied().collect();
+                self.reset_baseline(samples);
+            }
+
+            return None;
+        }
+
+        let std_dev = self.std_dev();
+        let z = (value - self.mean) / std_dev;
+
+        if self.cusum_pos == 0.0 {
+            self.run_pos_start = self.recent.len();
+        }
+        if self.cusum_neg == 0.0 {
+            self.run_neg_start = self.recent.len();
+        }
+        self.push_recent((time, value));
+
+        let spike = (z > SPIKE_Z && value - self.mean > SPIKE_MIN_DELTA).then_some(Anomaly {
+            time,
+            kind: AnomalyKind::Spike {
+                value,
+                baseline: self.mean,
+            },
+        });
+
+        let z = z.clamp(-CUSUM_Z_CLAMP, CUSUM_Z_CLAMP);
+        self.cusum_pos = (self.cusum_pos + z - CUSUM_K).max(0.0);
+        self.cusum_neg = (self.cusum_neg - z - CUSUM_K).max(0.0);
+
+        if self.cusum_pos > CUSUM_H {
+            return Some(self.level_shift(self.run_pos_start));
+        }
+        if self.cusum_neg > CUSUM_H {
+            return Some(self.level_shift(self.run_neg_start));
+        }
+
+        if spike.is_none() && self.cusum_pos == 0.0 && self.cusum_neg == 0.0 {
+            // Follow slow drift while nothing is accumulating
+            let delta = value - self.mean;
+            self.mean += BASELINE_ALPHA * delta;
+            self.var = (1.0 - BASELINE_ALPHA) * (self.var + BASELINE_ALPHA * delta.powi(2));
+        }
+
+        spike
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Replies once a second from `start`, with a little deterministic jitter around `level`
+    fn feed(
+        detector: &mut AnomalyDetector,
+        start: usize,
+        count: usize,
+        level: f64,
+    ) -> Vec<Anomaly> {
+        (start..start + count)
+            .filter_map(|idx| {
+                let jitter = 0.0002 * ((idx % 5) as f64 - 2.0);
+                detector.push(idx as f64, level + jitter)
+            })
+            .collect()
+    }
+
+    fn level_shifts(anomalies: &[Anomaly]) -> Vec<(f64, f64, f64)> {
+        anomalies
+            .iter()
+            .filter_map(|x| match x.kind {
+                AnomalyKind::LevelShift { before, after } => Some((x.time, before, after)),
+                AnomalyKind::Spike { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flat_series_never_flags() {
+        let mut detector = AnomalyDetector::default();
+
+        assert!(feed(&mut detector, 0, 500, 0.012).is_empty());
+    }
+
+    #[test]
+    fn step_is_one_level_shift_from_its_start() {
+        let mut detector = AnomalyDetector::default();
+        assert!(feed(&mut detector, 0, 60, 0.012).is_empty());
+
+        let anomalies = feed(&mut detector, 60, 60, 0.045);
+
+        let shifts = level_shifts(&anomalies);
+        assert_eq!(shifts.len(), 1);
+
+        let (time, before, after) = shifts[0];
+        assert_eq!(time, 60.0);
+        assert!((before - 0.012).abs() < 0.0005);
+        assert!((after - 0.045).abs() < 0.0005);
+    }
+
+    #[test]
+    fn single_outlier_is_a_spike() {
+        let mut detector = AnomalyDetector::default();
+        feed(&mut detector, 0, 60, 0.012);
+
+        let spike = detector.push(60.0, 0.1);
+        let after = feed(&mut detector, 61, 60, 0.012);
+
+        assert!(after.is_empty());
+        let Some(Anomaly {
+            time,
+            kind: AnomalyKind::Spike { value, baseline },
+        }) = spike
+        else {
+            panic!("outlier wasn't reported as a spike");
+        };
+        assert_eq!((time, value), (60.0, 0.1));
+        assert!((baseline - 0.012).abs() < 0.0005);
+    }
+
+    #[test]
+    fn rearms_after_warmup() {
+        let mut detector = AnomalyDetector::default();
+
+        // Nothing is flagged while the first baseline is still being learned
+        assert!(detector.push(0.0, 0.012).is_none());
+        assert!(detector.push(1.0, 0.2).is_none());
+        assert!(feed(&mut detector, 2, WARMUP_SAMPLES, 0.012).is_empty());
+        feed(&mut detector, 22, 60, 0.012);
+
+        assert_eq!(level_shifts(&feed(&mut detector, 82, 60, 0.045)).len(), 1);
+
+        // The shift restarts warm-up on the new level, after which a step back is caught too
+        let shifts = level_shifts(&feed(&mut detector, 142, 60, 0.012));
+        assert_eq!(shifts.len(), 1);
+
+        let (time, before, after) = shifts[0];
+        assert_eq!(time, 142.0);
+        assert!((before - 0.045).abs() < 0.0005);
+        assert!((after - 0.012).abs() < 0.0005);
+    }
+}
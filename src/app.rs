@@ -0,0 +1,385 @@
+use crate::{
+    alert, correlation, funcs,
+    graph::{PingGraph, TimeLayout},
+    probe, smoothing, style, view_alerts, view_correlation, view_events, view_heatmap,
+    view_histogram, yaxis, DEFAULT_TARGETS, MAX_HISTORY_SECS,
+};
+
+use serde::{Deserialize, Serialize};
+
+// The window size on first start and after resetting the settings
+pub const DEFAULT_WINDOW_SIZE: egui::Vec2 = egui::vec2(800.0, 600.0);
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum PlotView {
+    Time,
+    Histogram,
+    Heatmap,
+    Correlation,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct App {
+    #[serde(skip)]
+    probes: Option<probe::ProbeRuntime>,
+    targets: Vec<String>,
+    #[serde(skip)]
+    new_target: String,
+    #[serde(skip)]
+    new_target_error: Option<String>,
+
+    graph: PingGraph,
+
+    plot_view: PlotView,
+    histogram_opts: view_histogram::HistogramOptions,
+    heatmap_opts: view_heatmap::HeatmapOptions,
+    correlation_metric: view_correlation::CorrelationMetric,
+
+    #[serde(skip)]
+    alert_draft: alert::AlertRule,
+
+    // Set by clicking an event, switches to the time plot centered on it
+    #[serde(skip)]
+    plot_focus: Option<f64>,
+    #[serde(skip)]
+    event_export_status: Option<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            targets: DEFAULT_TARGETS.iter().map(|&x| x.to_string()).collect(),
+            new_target: String::new(),
+            new_target_error: None,
+
+            probes: None,
+
+            graph: Default::default(),
+
+            plot_view: PlotView::Time,
+            histogram_opts: Default::default(),
+            heatmap_opts: Default::default(),
+            correlation_metric: view_correlation::CorrelationMetric::Latency,
+
+            alert_draft: Default::default(),
+
+            plot_focus: None,
+            event_export_status: None,
+        }
+    }
+}
+
+impl App {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        probes: probe::ProbeRuntime,
+        targets: Vec<String>,
+        frame_interval: std::time::Duration,
+    ) -> Self {
+        let mut app: App = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+
+        app.graph.frame_interval = frame_interval;
+        if !targets.is_empty() {
+            app.targets = targets;
+        }
+        probes.repainter.attach(cc.egui_ctx.clone(), frame_interval);
+
+        app.graph.attach(&probes);
+
+        for target in &app.targets {
+            probes.add_target(target);
+        }
+        app.probes = Some(probes);
+
+        app
+    }
+
+    // Resets persisted UI settings while keeping the running probes and their target list
+    fn reset_settings(&mut self, ctx: &egui::Context) {
+        let frame_interval = self.graph.frame_interval;
+
+        *self = Self {
+            probes: self.probes.take(),
+            targets: std::mem::take(&mut self.targets),
+            ..Default::default()
+        };
+
+        self.graph.frame_interval = frame_interval;
+        if let Some(probes) = &self.probes {
+            self.graph.attach(probes);
+        }
+
+        // Widget state like plot zoom and collapsed sections, the rest of egui's memory is
+        // for the frame in progress
+        ctx.memory_mut(|mem| mem.data.clear());
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(false));
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(DEFAULT_WINDOW_SIZE));
+    }
+
+    fn add_target(&mut self, target: &str) {
+        if let Some(probes) = &self.probes {
+            probes.add_target(target.trim());
+        }
+        self.sync_targets();
+    }
+
+    fn remove_target(&mut self, target: &str) {
+        if let Some(probes) = &self.probes {
+            probes.remove_target(target);
+        }
+        self.sync_targets();
+    }
+
+    // The control API can change the probed targets too, so the probes own the list
+    fn sync_targets(&mut self) {
+        if let Some(probes) = &self.probes {
+            self.targets = probes.targets.targets();
+        }
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        puffin::profile_scope!("update");
+        let mut reset_settings = false;
+        self.sync_targets();
+
+        if let Some(focus) = self.plot_focus.take() {
+            self.plot_view = PlotView::Time;
+            self.graph.focus(focus);
+        }
+
+        let visible_range = self.graph.visible_range(ctx);
+        let highest_value = self.graph.get_highest_value_youngerthan(visible_range.0);
+
+        ctx.set_visuals(egui::Visuals::dark());
+
+        egui::TopBottomPanel::bottom("event_log")
+            .resizable(true)
+            .default_height(120.0)
+            .show(ctx, |ui| {
+                if let Ok(mut events) = self.graph.events().lock() {
+                    view_events::show(
+                        ui,
+                        &mut events,
+                        &mut self.plot_focus,
+                        &mut self.event_export_status,
+                    );
+                }
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            puffin::profile_scope!("CentralPanel_draw");
+
+            egui::CollapsingHeader::new("Options")
+                .default_open(false)
+                .show_unindented(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Window size");
+                        ui.add(egui::Slider::new(
+                            &mut self.graph.history_window,
+                            5.0..=MAX_HISTORY_SECS,
+                        ));
+                        ui.checkbox(&mut self.graph.show_anomalies, "Anomaly markers");
+                        ui.checkbox(&mut self.graph.show_timeouts, "Timeout markers");
+                        ui.checkbox(&mut self.graph.show_loss_strip, "Loss strip");
+                    });
+
+                    egui::CollapsingHeader::new("Targets")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                let response = ui.text_edit_singleline(&mut self.new_target);
+                                let submitted = response.lost_focus()
+                                    && ui.input(|i| i.key_pressed(egui::Key::Enter));
+
+                                if ui.button("Add").clicked() || submitted {
+                                    match probe::parse_target(&self.new_target) {
+                                        Ok(target) => {
+                                            self.new_target.clear();
+                                            self.new_target_error = None;
+                                            self.add_target(&target);
+                                        }
+                                        Err(e) => self.new_target_error = Some(e),
+                                    }
+                                }
+                            });
+                            if let Some(error) = &self.new_target_error {
+                                ui.colored_label(egui::Color32::RED, error);
+                            }
+
+                            let mut targets = self.targets.clone();
+                            if let Ok(ts_hash) = self.graph.timeseries().lock() {
+                                let mut remote: Vec<_> = ts_hash
+                                    .keys()
+                                    .filter(|x| !self.targets.contains(x))
+                                    .cloned()
+                                    .collect();
+                                remote.sort();
+                                targets.extend(remote);
+                            }
+
+                            if let Some(target) = style::show_options(
+                                ui,
+                                &targets,
+                                &self.targets.clone(),
+                                &mut self.graph.target_styles,
+                            ) {
+                                self.remove_target(&target);
+                            }
+                        });
+
+                    egui::CollapsingHeader::new("Y Axis")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            yaxis::show_options(ui, &mut self.graph.yaxis);
+                        });
+
+                    egui::CollapsingHeader::new("Extra Options")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.horizontal(|ui| {
+                                ui.label("Top Padding");
+                                ui.add(egui::Slider::new(&mut self.graph.top_padding, 0.05..=2.0));
+                            });
+
+                            if ui.button("Reset to defaults").clicked() {
+                                reset_settings = true;
+                            }
+                        });
+
+                    egui::CollapsingHeader::new("Histogram")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            view_histogram::show_options(ui, &mut self.histogram_opts);
+                        });
+
+                    egui::CollapsingHeader::new("Heatmap")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            view_heatmap::show_options(ui, &mut self.heatmap_opts);
+                        });
+
+                    egui::CollapsingHeader::new("Smoothing")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            let mut targets: Vec<_> = match self.graph.timeseries().lock() {
+                                Ok(ts_hash) => ts_hash.keys().cloned().collect(),
+                                Err(_) => vec![],
+                            };
+                            targets.sort();
+
+                            smoothing::show_options(ui, &targets, &mut self.graph.smoothing);
+                        });
+
+                    egui::CollapsingHeader::new("Alerts")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            if let Ok(mut alerts) = self.graph.alerts().lock() {
+                                view_alerts::show_options(ui, &mut alerts, &mut self.alert_draft);
+                            }
+                        });
+                });
+
+            self.graph.show_details(ui);
+
+            let correlation = match self.graph.timeseries().lock() {
+                Ok(ts_hash) => Some(correlation::analyze(&ts_hash, visible_range)),
+                Err(_) => None,
+            };
+
+            if let Some(report) = &correlation {
+                view_correlation::show_diagnosis(ui, &report.diagnosis);
+            }
+
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.plot_view, PlotView::Time, "Time");
+                ui.selectable_value(&mut self.plot_view, PlotView::Histogram, "Histogram");
+                ui.selectable_value(&mut self.plot_view, PlotView::Heatmap, "Heatmap");
+                ui.selectable_value(&mut self.plot_view, PlotView::Correlation, "Correlation");
+
+                if self.plot_view == PlotView::Time {
+                    ui.separator();
+                    ui.selectable_value(
+                        &mut self.graph.time_layout,
+                        TimeLayout::Overlay,
+                        "Overlay",
+                    );
+                    ui.selectable_value(
+                        &mut self.graph.time_layout,
+                        TimeLayout::SmallMultiples,
+                        "Per target",
+                    );
+
+                    if self.graph.time_layout == TimeLayout::SmallMultiples {
+                        ui.checkbox(&mut self.graph.shared_y, "Shared Y");
+                    }
+                }
+
+                ui.separator();
+                ui.toggle_value(&mut self.graph.follow_live, "Follow live");
+
+                if !self.graph.follow_live {
+                    ui.label(format!(
+                        "Paused at {} - {}",
+                        funcs::x_axis_fmt(self.graph.clock().as_ref(), visible_range.0),
+                        funcs::x_axis_fmt(self.graph.clock().as_ref(), visible_range.1)
+                    ));
+                    if ui.button("Back to live").clicked() {
+                        self.graph.follow_live = true;
+                    }
+                }
+            });
+
+            match self.plot_view {
+                PlotView::Time => self.graph.show_plot(ui),
+                PlotView::Histogram => {
+                    if let Ok(asdf) = self.graph.timeseries().lock() {
+                        view_histogram::show(
+                            ui,
+                            &asdf,
+                            &self.graph.target_styles,
+                            visible_range.0,
+                            &self.histogram_opts,
+                        );
+                    }
+                }
+                PlotView::Heatmap => {
+                    if let Ok(asdf) = self.graph.timeseries().lock() {
+                        view_heatmap::show(
+                            ui,
+                            &asdf,
+                            &self.graph.target_styles,
+                            visible_range,
+                            highest_value + self.graph.top_padding,
+                            &self.heatmap_opts,
+                            self.graph.clock().as_ref(),
+                        );
+                    }
+                }
+                PlotView::Correlation => {
+                    if let Some(report) = &correlation {
+                        view_correlation::show(ui, report, &mut self.correlation_metric);
+                    }
+                }
+            }
+        });
+
+        if reset_settings {
+            self.reset_settings(ctx);
+        }
+
+        self.graph.request_repaint(ctx);
+
+        puffin::GlobalProfiler::lock().new_frame();
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+}
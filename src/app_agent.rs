@@ -0,0 +1,323 @@
+use crate::{
+    agent::{self, AgentConfig, AgentMessage, ViewerHello},
+    clock::SharedClock,
+    events::{EventKind, EventLog},
+    probe::{Repainter, TargetManager},
+    series::Series,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use color_eyre::eyre::{eyre, Report};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpStream,
+    time::Duration,
+};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// How far ahead of this host's clock an agent's samples may be before they're dropped
+const MAX_CLOCK_SKEW_SECS: f64 = 60.0;
+
+struct AgentClient {
+    config: AgentConfig,
+    clock: SharedClock,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    events: Arc<Mutex<EventLog>>,
+    repainter: Repainter,
+    targets: Arc<TargetManager>,
+    // Fixed by the config or the first hello, so series keep their names across reconnects
+    name: OnceLock<String>,
+}
+
+impl AgentClient {
+    fn get_sec_elapsed(&self) -> f64 {
+        self.clock.elapsed()
+    }
+
+    fn prefix(&self) -> Option<String> {
+        self.name.get().map(|name| format!("{name}/"))
+    }
+
+    fn label(&self) -> &str {
+        self.name.get().unwrap_or(&self.config.addr)
+    }
+
+    // Newest sample every target of this agent already has, so a reconnect only
+    // backfills what's missing
+    fn since(&self) -> Option<f64> {
+        let prefix = self.prefix()?;
+        let ts_hash = self.timeseries_hashref.lock().ok()?;
+
+        ts_hash
+            .iter()
+            .filter(|(target, _)| target.starts_with(&prefix))
+            .filter_map(|(_, series)| series.last_sample_time())
+            .reduce(f64::min)
+            .map(|x| self.clock.unix_time_at(x))
+    }
+
+    fn ingest(&self, target: &str, time: f64, rtt: Option<f64>) {
+        let Some(prefix) = self.prefix() else {
+            return;
+        };
+        let name = format!("{prefix}{target}");
+        let time = self.clock.elapsed_at_unix_time(time);
+
+        // Out of range times would overflow formatting them, and one far in the future would
+        // hide every later sample behind the dedup below
+        let now = self.get_sec_elapsed();
+        let window = now - crate::MAX_HISTORY_SECS..=now + MAX_CLOCK_SKEW_SECS;
+        if !window.contains(&time) || rtt.is_some_and(|x| !x.is_finite() || x < 0.0) {
+            tracing::debug!(
+                "Dropping out of range sample for {name} from {}",
+                self.label()
+            );
+            return;
+        }
+
+        let Ok(mut ts_hash) = self.timeseries_hashref.lock() else {
+            return;
+        };
+        let series = ts_hash
+            .entry(name.clone())
+            .or_insert_with(|| Series::with_idxcolor(self.targets.next_color()));
+
+        // Backfill overlaps with what already arrived before the connection dropped
+        if series.last_sample_time().is_some_and(|last| time <= last) {
+            return;
+        }
+
+        let anomaly = match rtt {
+            Some(rtt) => series.push(Some((time, rtt))),
+            None => {
+                series.timeout_at.push(time);
+                series.push(None)
+            }
+        };
+        drop(ts_hash);
+
+        if let (Some(anomaly), Ok(mut events)) = (anomaly, self.events.lock()) {
+            events.push(anomaly.time, &name, EventKind::Anomaly(anomaly));
+        }
+    }
+
+    async fn stream(&self, backoff: &mut Duration) -> Result<(), Report> {
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&self.config.addr))
+            .await
+            .map_err(|_| eyre!("Timed out connecting"))??;
+        let (read, mut write) = stream.into_split();
+
+        let hello = serde_json::to_string(&ViewerHello {
+            since: self.since(),
+        })?;
+        write.write_all(format!("{hello}\n").as_bytes()).await?;
+
+        let mut lines = BufReader::new(read).lines();
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        let mut last_message = tokio::time::Instant::now();
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let line = line?.ok_or_else(|| eyre!("Connection closed"))?;
+                    last_message = tokio::time::Instant::now();
+
+                    match serde_json::from_str(&line)? {
+                        AgentMessage::Hello { agent } => {
+                            self.name.get_or_init(|| agent);
+                            *backoff = MIN_BACKOFF;
+                            tracing::info!("Connected to agent {} at {}", self.label(), self.config.addr);
+                        }
+                        AgentMessage::Sample { target, time, rtt } => {
+                            self.ingest(&target, time, rtt);
+                            self.repainter.request();
+                        }
+                        AgentMessage::Heartbeat => {}
+                        AgentMessage::Unknown => {
+                            tracing::debug!("Skipping unknown message from agent {}: {line}", self.label());
+                        }
+                    }
+                }
+                _ = interval.tick() => {
+                    if last_message.elapsed() > agent::HEARTBEAT_INTERVAL * 3 {
+                        return Err(eyre!("Agent stopped responding"));
+                    }
+                }
+            }
+        }
+    }
+
+    // Remote series have no cleaner of their own, and have to age out while disconnected too
+    async fn clean(&self) -> Result<(), Report> {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+
+        loop {
+            interval.tick().await;
+
+            let (Some(prefix), Ok(mut ts_hash)) = (self.prefix(), self.timeseries_hashref.lock())
+            else {
+                continue;
+            };
+
+            let cutoff = self.get_sec_elapsed() - crate::MAX_HISTORY_SECS - 1.0;
+
+            // Targets the agent stopped probing have nothing left inside the history window
+            ts_hash.retain(|target, series| {
+                !target.starts_with(&prefix)
+                    || series.last_sample_time().is_some_and(|x| x > cutoff)
+            });
+
+            for (_, series) in ts_hash.iter_mut().filter(|x| x.0.starts_with(&prefix)) {
+                series.remove_older_than(cutoff);
+                series.update_pingstat();
+            }
+        }
+    }
+
+    async fn connect_loop(&self) -> Result<(), Report> {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            if let Err(e) = self.stream(&mut backoff).await {
+                if let Ok(mut events) = self.events.lock() {
+                    events.push(
+                        self.get_sec_elapsed(),
+                        self.label(),
+                        EventKind::ProbeError(format!("Agent {}: {e}", self.config.addr)),
+                    );
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+// Shows the targets of a remote agent as `<agent>/<target>` series, reconnecting as needed
+pub async fn run_agent_client(
+    config: AgentConfig,
+    clock: SharedClock,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    events: Arc<Mutex<EventLog>>,
+    repainter: Repainter,
+    targets: Arc<TargetManager>,
+) -> Result<(), Report> {
+    let name = OnceLock::new();
+    if let Some(config_name) = &config.name {
+        name.set(config_name.clone()).ok();
+    }
+
+    let client = AgentClient {
+        config,
+        clock,
+        timeseries_hashref,
+        events,
+        repainter,
+        targets,
+        name,
+    };
+
+    tokio::select! {
+        result = client.connect_loop() => result,
+        result = client.clean() => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, config::Config, probe::ProbeRuntime};
+
+    const START: i64 = 1_704_067_200;
+
+    fn client(probes: &ProbeRuntime) -> AgentClient {
+        AgentClient {
+            config: AgentConfig {
+                addr: "127.0.0.1:9900".to_string(),
+                name: Some("ag".to_string()),
+            },
+            clock: probes.clock(),
+            timeseries_hashref: probes.timeseries.clone(),
+            events: probes.events.clone(),
+            repainter: probes.repainter.clone(),
+            targets: probes.targets.clone(),
+            name: OnceLock::from("ag".to_string()),
+        }
+    }
+
+    fn start() -> (Arc<ManualClock>, ProbeRuntime) {
+        let clock = Arc::new(ManualClock::new(
+            time::OffsetDateTime::from_unix_timestamp(START).unwrap(),
+        ));
+        let probes = ProbeRuntime::start_with_clock(Config::default(), clock.clone()).unwrap();
+
+        (clock, probes)
+    }
+
+    fn times(probes: &ProbeRuntime) -> Vec<f64> {
+        let ts_hash = probes.timeseries.lock().unwrap();
+        ts_hash["ag/host"]
+            .samples_younger_than(f64::NEG_INFINITY)
+            .iter()
+            .map(|x| x.time)
+            .collect()
+    }
+
+    #[test]
+    fn backfill_skips_samples_already_seen() {
+        let (clock, probes) = start();
+        let client = client(&probes);
+        clock.set(10.0);
+
+        for time in [1.0, 2.0, 3.0] {
+            client.ingest("host", START as f64 + time, Some(0.01));
+        }
+        assert_eq!(client.since(), Some(START as f64 + 3.0));
+
+        // A reconnect replays from a little before the newest sample
+        client.ingest("host", START as f64 + 2.0, Some(0.01));
+        client.ingest("host", START as f64 + 3.0, None);
+        client.ingest("host", START as f64 + 4.0, None);
+        client.ingest("host", START as f64 + 5.0, Some(0.02));
+
+        assert_eq!(times(&probes), [1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn out_of_range_times_are_dropped() {
+        let (clock, probes) = start();
+        let client = client(&probes);
+        clock.set(200.0);
+
+        client.ingest("host", 1e20, Some(0.01));
+        client.ingest("host", f64::NAN, Some(0.01));
+        client.ingest("host", f64::INFINITY, None);
+        client.ingest(
+            "host",
+            START as f64 + 200.0 + MAX_CLOCK_SKEW_SECS + 1.0,
+            None,
+        );
+        client.ingest("host", START as f64 + 10.0, Some(0.01));
+        client.ingest("host", START as f64 + 150.0, Some(f64::NAN));
+
+        // None of them hold back the samples that follow
+        client.ingest("host", START as f64 + 150.0, Some(0.01));
+        client.ingest("host", START as f64 + 210.0, Some(0.01));
+
+        assert_eq!(times(&probes), [150.0, 210.0]);
+    }
+
+    #[test]
+    fn unknown_message_types_parse() {
+        let message = serde_json::from_str(r#"{"type": "capabilities", "ipv6": true}"#).unwrap();
+
+        assert!(matches!(message, AgentMessage::Unknown));
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    alert::AlertEngine,
+    clock::SharedClock,
+    events::{EventKind, EventLog},
+    notify::Notifier,
+    series::Series,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::Report;
+
+pub async fn run_alerter(
+    clock: SharedClock,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    alerts: Arc<Mutex<AlertEngine>>,
+    mut notifier: Notifier,
+    event_log: Arc<Mutex<EventLog>>,
+) -> Result<(), Report> {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+
+        let now = clock.elapsed();
+        let events = match (timeseries_hashref.lock(), alerts.lock()) {
+            (Ok(ts_hash), Ok(mut alerts)) => {
+                alerts.evaluate(&ts_hash, now);
+                alerts.remove_older_than(now - crate::MAX_HISTORY_SECS - 1.0);
+                std::mem::take(&mut alerts.outbox)
+            }
+            _ => vec![],
+        };
+
+        if let Ok(mut event_log) = event_log.lock() {
+            for event in &events {
+                event_log.push(
+                    event.time,
+                    &event.target,
+                    EventKind::Alert {
+                        rule: event.rule.name.clone(),
+                        transition: event.transition,
+                    },
+                );
+            }
+        }
+
+        for event in events {
+            notifier.dispatch(event);
+        }
+    }
+}
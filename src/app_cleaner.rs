@@ -1,4 +1,7 @@
-use crate::series::Series;
+use crate::{
+    clock::{Clock, SharedClock},
+    series::Series,
+};
 
 use std::{
     collections::HashMap,
@@ -9,20 +12,63 @@ use color_eyre::eyre::Report;
 
 pub async fn run_cleaner(
     target: String,
-    start_time: time::Instant,
+    clock: SharedClock,
     timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
 ) -> Result<(), Report> {
     let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
-    let get_sec_elapsed = || (time::Instant::now() - start_time).as_seconds_f64();
 
     loop {
         if let Ok(mut ts_hash) = timeseries_hashref.lock() {
             if let Some(ts) = ts_hash.get_mut(&target) {
-                ts.remove_older_than(get_sec_elapsed() - crate::MAX_HISTORY_SECS - 1.0);
-                ts.update_pingstat();
+                clean(ts, clock.as_ref());
             }
         }
 
         interval.tick().await;
     }
 }
+
+fn clean(ts: &mut Series, clock: &dyn Clock) {
+    ts.remove_older_than(clock.elapsed() - crate::MAX_HISTORY_SECS - 1.0);
+    ts.update_pingstat();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, MAX_HISTORY_SECS};
+
+    #[test]
+    fn keeps_only_the_history_window() {
+        let clock =
+            ManualClock::new(time::OffsetDateTime::from_unix_timestamp(1_704_067_200).unwrap());
+        let mut ts = Series::default();
+
+        for _ in 0..3 {
+            clock.advance(1.0);
+            ts.push(Some((clock.elapsed(), 0.01)));
+        }
+        ts.timeout_at.push(3.5);
+        clock.advance(1.0);
+        ts.push(Some((clock.elapsed(), 0.04)));
+
+        // Nothing has aged out yet
+        clock.set(MAX_HISTORY_SECS + 1.5);
+        clean(&mut ts, &clock);
+        assert_eq!(ts.raw.len(), 4);
+        assert_eq!(ts.stats.max, 0.04);
+
+        // The window has moved past the first two replies
+        clock.set(MAX_HISTORY_SECS + 3.0);
+        clean(&mut ts, &clock);
+        assert_eq!(ts.raw, [Some((3.0, 0.01)), Some((4.0, 0.04))]);
+        assert_eq!(ts.timeout_at, [3.5]);
+
+        // And past everything
+        clock.advance(10.0);
+        clean(&mut ts, &clock);
+        assert!(ts.raw.is_empty());
+        assert!(ts.timeout_at.is_empty());
+        assert_eq!(ts.stats.last, 0.0);
+    }
+}
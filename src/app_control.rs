@@ -0,0 +1,172 @@
+use crate::control::Control;
+
+use std::sync::Arc;
+
+use color_eyre::eyre::Report;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+// Requests are a few short fields, this only stops a client from growing the buffer forever
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+async fn handle_connection(
+    stream: impl AsyncRead + AsyncWrite,
+    control: Arc<Control>,
+) -> Result<(), Report> {
+    let (read, mut write) = tokio::io::split(stream);
+    let mut reader = BufReader::new(read);
+    let mut buf = vec![];
+
+    loop {
+        buf.clear();
+        let read = (&mut reader)
+            .take(MAX_LINE_LEN as u64 + 1)
+            .read_until(b'\n', &mut buf)
+            .await?;
+        if read == 0 {
+            return Ok(());
+        }
+
+        if buf.len() > MAX_LINE_LEN {
+            let response = serde_json::json!({ "ok": false, "error": "Request too long" });
+            write.write_all(format!("{response}\n").as_bytes()).await?;
+            return Ok(());
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Anything that isn't JSON, like the request line of a browser POST, ends the
+        // connection before a body smuggling commands in gets read
+        if serde_json::from_str::<serde_json::Value>(&line).is_err() {
+            let response = serde_json::json!({ "ok": false, "error": "Not a JSON request" });
+            write.write_all(format!("{response}\n").as_bytes()).await?;
+            return Ok(());
+        }
+
+        let response = control.handle_line(&line);
+        write.write_all(format!("{response}\n").as_bytes()).await?;
+    }
+}
+
+pub async fn run_control_tcp(listener: TcpListener, control: Arc<Control>) -> Result<(), Report> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let control = control.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, control).await {
+                tracing::debug!("Control connection from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(unix)]
+pub async fn run_control_unix(
+    listener: tokio::net::UnixListener,
+    control: Arc<Control>,
+) -> Result<(), Report> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let control = control.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, control).await {
+                tracing::debug!("Control connection failed: {e}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, probe::ProbeRuntime};
+
+    fn control(probes: &ProbeRuntime) -> Arc<Control> {
+        Arc::new(Control {
+            clock: probes.clock(),
+            timeseries: probes.timeseries.clone(),
+            events: probes.events.clone(),
+            targets: probes.targets.clone(),
+        })
+    }
+
+    async fn exchange(control: Arc<Control>, input: &[u8]) -> Vec<serde_json::Value> {
+        let (client, server) = tokio::io::duplex(4096);
+        let connection = tokio::spawn(handle_connection(server, control));
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+
+        // Written alongside reading, the server may hang up before it has read everything
+        let input = input.to_vec();
+        tokio::spawn(async move {
+            client_write.write_all(&input).await.ok();
+            client_write.shutdown().await.ok();
+        });
+
+        let mut output = String::new();
+        client_read.read_to_string(&mut output).await.unwrap();
+        connection.await.unwrap().unwrap();
+
+        output
+            .lines()
+            .map(|x| serde_json::from_str(x).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn answers_each_request_line() {
+        let probes = ProbeRuntime::start(Config::default()).unwrap();
+        let input = b"{\"cmd\": \"targets\"}\n\n{\"cmd\": \"nope\"}\n{\"cmd\": \"targets\"}\n";
+
+        let responses = probes.block_on(exchange(control(&probes), input));
+
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["ok"], true);
+        assert_eq!(responses[1]["ok"], false);
+        assert_eq!(responses[2]["ok"], true);
+    }
+
+    #[test]
+    fn closes_on_http_request() {
+        let probes = ProbeRuntime::start(Config::default()).unwrap();
+        let input = b"POST / HTTP/1.1\r\nHost: 127.0.0.1\r\n\r\n{\"cmd\": \"add\", \"target\": \"10.0.0.1\"}\n";
+
+        let responses = probes.block_on(exchange(control(&probes), input));
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["ok"], false);
+        assert!(probes.targets.targets().is_empty());
+    }
+
+    #[test]
+    fn rejects_option_targets() {
+        let probes = ProbeRuntime::start(Config::default()).unwrap();
+        let input = b"{\"cmd\": \"add\", \"target\": \"-f\"}\n\
+                      {\"cmd\": \"add\", \"target\": \"-s 65000\"}\n\
+                      {\"cmd\": \"add\", \"target\": \"host\\u0000\"}\n";
+
+        let responses = probes.block_on(exchange(control(&probes), input));
+
+        assert_eq!(responses.len(), 3);
+        assert!(responses.iter().all(|x| x["ok"] == false));
+        assert!(probes.targets.targets().is_empty());
+    }
+
+    #[test]
+    fn closes_on_overlong_line() {
+        let probes = ProbeRuntime::start(Config::default()).unwrap();
+        let mut input = vec![b' '; MAX_LINE_LEN * 2];
+        input.extend(b"\n{\"cmd\": \"targets\"}\n");
+
+        let responses = probes.block_on(exchange(control(&probes), &input));
+
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["error"], "Request too long");
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{
+    clock::SharedClock,
+    export::{self, ExportBuffer, ExporterConfig, ExporterKind},
+    notify,
+    series::{SampleCursor, Series},
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::{eyre, Report, WrapErr};
+use tokio::{net::UdpSocket, time::Duration};
+
+// Lines sent per HTTP request
+const MAX_BATCH: usize = 5000;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+// Counts delivered lines into `sent`, so a failure part way through only keeps the rest
+async fn send_udp(addr: &str, lines: &[String], sent: &mut usize) -> Result<(), Report> {
+    let peer = tokio::net::lookup_host(addr)
+        .await
+        .wrap_err_with(|| format!("Failed to resolve {addr}"))?
+        .next()
+        .ok_or_else(|| eyre!("{addr} did not resolve to any address"))?;
+
+    let bind_addr = if peer.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+
+    for (datagram, count) in export::pack_datagrams(lines) {
+        socket
+            .send_to(datagram.as_bytes(), peer)
+            .await
+            .wrap_err_with(|| format!("Failed to send to {addr}"))?;
+        *sent += count;
+    }
+
+    Ok(())
+}
+
+async fn send(kind: &ExporterKind, lines: &[String], sent: &mut usize) -> Result<(), Report> {
+    match kind {
+        ExporterKind::InfluxHttp { url } => {
+            notify::http_post(
+                url,
+                "text/plain; charset=utf-8",
+                lines.join("\n").as_bytes(),
+            )
+            .await?;
+            *sent = lines.len();
+            Ok(())
+        }
+        ExporterKind::InfluxUdp { addr } | ExporterKind::Statsd { addr, .. } => {
+            send_udp(addr, lines, sent).await
+        }
+    }
+}
+
+async fn flush(buffer: &mut ExportBuffer) -> Result<(), Report> {
+    if buffer.dropped > 0 {
+        tracing::warn!(
+            "{}: dropped {} buffered samples",
+            buffer.config.describe(),
+            buffer.dropped
+        );
+        buffer.dropped = 0;
+    }
+
+    while !buffer.is_empty() {
+        let batch = buffer.peek(MAX_BATCH);
+        let mut sent = 0;
+        let result = send(&buffer.config.kind, &batch, &mut sent).await;
+
+        buffer.consume(sent);
+        result?;
+    }
+
+    Ok(())
+}
+
+// Pushes every new sample to the configured endpoint, holding on to them while it is down
+pub async fn run_exporter(
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    config: ExporterConfig,
+    clock: SharedClock,
+) -> Result<(), Report> {
+    let flush_interval = Duration::from_secs_f64(config.flush_secs.max(1.0));
+    let mut buffer = ExportBuffer::new(config, clock);
+    let mut cursor = SampleCursor::default();
+
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    let mut next_flush = tokio::time::Instant::now() + flush_interval;
+    let mut backoff = flush_interval;
+
+    loop {
+        interval.tick().await;
+
+        if let Ok(ts_hash) = timeseries_hashref.lock() {
+            for (target, series) in ts_hash.iter() {
+                for sample in cursor.read_new(target, series) {
+                    buffer.push(target, &sample);
+                }
+            }
+            cursor.retain(|target| ts_hash.contains_key(target));
+        }
+
+        if tokio::time::Instant::now() < next_flush {
+            continue;
+        }
+
+        match flush(&mut buffer).await {
+            Ok(_) => {
+                backoff = flush_interval;
+                next_flush = tokio::time::Instant::now() + flush_interval;
+            }
+            Err(e) => {
+                backoff = (backoff * 2).min(MAX_BACKOFF.max(flush_interval));
+                next_flush = tokio::time::Instant::now() + backoff;
+
+                tracing::warn!(
+                    "{}: {e}, retrying in {}s",
+                    buffer.config.describe(),
+                    backoff.as_secs()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{clock::ManualClock, series::Sample};
+
+    fn buffer(kind: ExporterKind) -> ExportBuffer {
+        let config = ExporterConfig {
+            kind,
+            flush_secs: 1.0,
+            max_buffer: 100,
+        };
+        let clock =
+            ManualClock::new(time::OffsetDateTime::from_unix_timestamp(1_704_067_200).unwrap());
+
+        ExportBuffer::new(config, Arc::new(clock))
+    }
+
+    fn push_samples(buffer: &mut ExportBuffer) {
+        buffer.push(
+            "host a",
+            &Sample {
+                time: 1.0,
+                rtt: Some(0.012),
+            },
+        );
+        buffer.push(
+            "host a",
+            &Sample {
+                time: 2.0,
+                rtt: None,
+            },
+        );
+    }
+
+    #[tokio::test]
+    async fn influx_udp_reaches_local_listener() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut buffer = buffer(ExporterKind::InfluxUdp {
+            addr: socket.local_addr().unwrap().to_string(),
+        });
+        push_samples(&mut buffer);
+
+        flush(&mut buffer).await.unwrap();
+        assert!(buffer.is_empty());
+
+        let mut datagram = [0; 1500];
+        let len = socket.recv(&mut datagram).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&datagram[..len]),
+            "ping,target=host\\ a rtt=0.012,timeout=false 1704067201000000000\n\
+             ping,target=host\\ a timeout=true 1704067202000000000"
+        );
+    }
+
+    #[tokio::test]
+    async fn statsd_reaches_local_listener() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut buffer = buffer(ExporterKind::Statsd {
+            addr: socket.local_addr().unwrap().to_string(),
+            prefix: "ping".to_string(),
+        });
+        push_samples(&mut buffer);
+
+        flush(&mut buffer).await.unwrap();
+
+        let mut datagram = [0; 1500];
+        let len = socket.recv(&mut datagram).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&datagram[..len]),
+            "ping.host_a.rtt:12.000|ms\nping.host_a.timeout:1|c"
+        );
+    }
+
+    #[tokio::test]
+    async fn influx_http_reaches_local_listener() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut buffer = buffer(ExporterKind::InfluxHttp {
+            url: format!("http://{}/write?db=ping", listener.local_addr().unwrap()),
+        });
+        push_samples(&mut buffer);
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+
+            while !String::from_utf8_lossy(&request).ends_with("1704067202000000000") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        flush(&mut buffer).await.unwrap();
+        assert!(buffer.is_empty());
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /write?db=ping HTTP/1.1\r\n"));
+        assert!(request.contains("\r\n\r\nping,target=host\\ a rtt=0.012"));
+    }
+
+    #[tokio::test]
+    async fn influx_http_keeps_lines_while_down() {
+        // Bound and dropped again, so nothing is listening on the port
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut buffer = buffer(ExporterKind::InfluxHttp {
+            url: format!("http://{addr}/write"),
+        });
+        push_samples(&mut buffer);
+
+        assert!(flush(&mut buffer).await.is_err());
+        assert_eq!(buffer.peek(10).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn udp_keeps_lines_that_fail_to_send() {
+        // Port 0 can't be sent to, so this fails without touching the network
+        let mut buffer = buffer(ExporterKind::Statsd {
+            addr: "127.0.0.1:0".to_string(),
+            prefix: "ping".to_string(),
+        });
+        push_samples(&mut buffer);
+
+        assert!(flush(&mut buffer).await.is_err());
+        assert_eq!(buffer.peek(10).len(), 2);
+
+        // Delivered on the next flush once the endpoint works
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        buffer.config.kind = ExporterKind::Statsd {
+            addr: socket.local_addr().unwrap().to_string(),
+            prefix: "ping".to_string(),
+        };
+        flush(&mut buffer).await.unwrap();
+        assert!(buffer.is_empty());
+
+        let mut datagram = [0; 1500];
+        let len = socket.recv(&mut datagram).await.unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&datagram[..len]),
+            "ping.host_a.rtt:12.000|ms\nping.host_a.timeout:1|c"
+        );
+    }
+}
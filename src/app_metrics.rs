@@ -0,0 +1,104 @@
+use crate::{clock::SharedClock, events::EventLog, metrics::Metrics, series::Series};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::Report;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+
+pub async fn run_metrics_collector(
+    clock: SharedClock,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    event_log: Arc<Mutex<EventLog>>,
+    metrics: Arc<Mutex<Metrics>>,
+) -> Result<(), Report> {
+    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
+
+    loop {
+        interval.tick().await;
+
+        if let (Ok(ts_hash), Ok(event_log), Ok(mut metrics)) =
+            (timeseries_hashref.lock(), event_log.lock(), metrics.lock())
+        {
+            metrics.ingest(&ts_hash, &event_log, clock.elapsed());
+        }
+    }
+}
+
+async fn read_request_line(stream: &mut TcpStream) -> Result<String, Report> {
+    let mut head = vec![];
+    let mut buf = [0; 1024];
+
+    while !head.windows(4).any(|x| x == b"\r\n\r\n") && head.len() < MAX_REQUEST_HEAD {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+
+    Ok(String::from_utf8_lossy(&head)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    metrics: Arc<Mutex<Metrics>>,
+) -> Result<(), Report> {
+    let request_line =
+        tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(&mut stream)).await??;
+    let mut parts = request_line.split_whitespace();
+
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = match metrics.lock() {
+                Ok(metrics) => metrics.render(),
+                Err(_) => String::new(),
+            };
+            ("200 OK", "text/plain; version=0.0.4", body)
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed\n".to_string(),
+        ),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+pub async fn run_metrics_server(
+    listener: TcpListener,
+    metrics: Arc<Mutex<Metrics>>,
+) -> Result<(), Report> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let metrics = metrics.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, metrics).await {
+                tracing::debug!("Metrics request from {peer} failed: {e}");
+            }
+        });
+    }
+}
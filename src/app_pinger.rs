@@ -1,32 +1,66 @@
-use crate::series::Series;
+use crate::{
+    clock::SharedClock,
+    events::{EventKind, EventLog},
+    probe::Repainter,
+    series::Series,
+};
 
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
-use color_eyre::eyre::{bail, Report};
+use color_eyre::eyre::{bail, eyre, Report};
 use pinger::ping_with_interval;
 
+// Consecutive timeouts before a target counts as being in an outage
+const OUTAGE_MIN_TIMEOUTS: u32 = 3;
+const MIN_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+const MAX_RESTART_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+pub const PING_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
 pub fn run_pinger(
     target: String,
-    start_time: time::Instant,
+    clock: SharedClock,
     timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
-    idxcolor: u8,
+    idxcolor: usize,
+    events: Arc<Mutex<EventLog>>,
+    repainter: Repainter,
+    stop: Arc<AtomicBool>,
 ) -> Result<(), Report> {
-    let get_sec_elapsed = || (time::Instant::now() - start_time).as_seconds_f64();
+    let get_sec_elapsed = || clock.elapsed();
+    let log_event = |time: f64, kind: EventKind| {
+        if let Ok(mut events) = events.lock() {
+            events.push(time, &target, kind);
+        }
+    };
+
+    let stream = ping_with_interval(target.clone(), PING_INTERVAL, None)
+        .map_err(|e| eyre!("Failed to start ping: {e}"))?;
 
-    let stream =
-        ping_with_interval(target.clone(), std::time::Duration::from_millis(250), None).unwrap();
+    let mut consecutive_timeouts = 0;
+    let mut first_timeout = 0.0;
 
     while let Ok(pingres) = stream.recv() {
         if let Ok(mut ts_hash) = timeseries_hashref.lock() {
             use pinger::PingResult;
 
+            // Checked under the lock so a removed target never gets re-inserted
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
             if let Some(ts) = ts_hash.get_mut(&target) {
-                ts.raw.push(match pingres {
+                let timed_out = matches!(pingres, PingResult::Timeout(_));
+                let sample = match pingres {
                     PingResult::Pong(dur, _) => Some((get_sec_elapsed(), dur.as_secs_f64())),
-                    PingResult::Timeout(_) => None,
+                    PingResult::Timeout(_) => {
+                        ts.timeout_at.push(get_sec_elapsed());
+                        None
+                    }
                     PingResult::Unknown(_) => None,
 
                     PingResult::PingExited(e, _) if e.success() => {
@@ -35,12 +69,107 @@ pub fn run_pinger(
                     PingResult::PingExited(e, stderr) => {
                         bail!("There was an error running ping: {e}\nStderr: {stderr}\n");
                     }
-                });
+                };
+
+                match (sample, ts.timeout_at.last()) {
+                    (Some((time, _)), _) => {
+                        if consecutive_timeouts >= OUTAGE_MIN_TIMEOUTS {
+                            log_event(
+                                time,
+                                EventKind::OutageEnd {
+                                    duration: time - first_timeout,
+                                },
+                            );
+                        }
+                        consecutive_timeouts = 0;
+                    }
+                    (None, Some(&time)) if timed_out => {
+                        if consecutive_timeouts == 0 {
+                            first_timeout = time;
+                        }
+                        consecutive_timeouts += 1;
+
+                        if consecutive_timeouts == OUTAGE_MIN_TIMEOUTS {
+                            log_event(first_timeout, EventKind::OutageStart);
+                        }
+                    }
+                    _ => {}
+                }
+
+                if let Some(anomaly) = ts.push(sample) {
+                    tracing::warn!("{}", anomaly.describe(&target, clock.as_ref()));
+                    log_event(anomaly.time, EventKind::Anomaly(anomaly));
+                }
             } else {
                 ts_hash.insert(target.clone(), Series::with_idxcolor(idxcolor));
             }
         }
+
+        repainter.request();
     }
 
     Ok::<(), Report>(())
 }
+
+// Keeps the blocking pinger running on its own thread, restarting it whenever ping exits
+pub async fn run_pinger_supervised(
+    target: String,
+    clock: SharedClock,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    idxcolor: usize,
+    events: Arc<Mutex<EventLog>>,
+    repainter: Repainter,
+    stop: Arc<AtomicBool>,
+) -> Result<(), Report> {
+    let get_sec_elapsed = || clock.elapsed();
+    let mut restart_delay = MIN_RESTART_DELAY;
+
+    loop {
+        let started = tokio::time::Instant::now();
+        let result = {
+            let target = target.clone();
+            let clock = clock.clone();
+            let timeseries_hashref = timeseries_hashref.clone();
+            let events = events.clone();
+            let repainter = repainter.clone();
+            let stop = stop.clone();
+
+            tokio::task::spawn_blocking(move || {
+                run_pinger(
+                    target,
+                    clock,
+                    timeseries_hashref,
+                    idxcolor,
+                    events,
+                    repainter,
+                    stop,
+                )
+            })
+            .await?
+        };
+
+        if stop.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        if let (Err(e), Ok(mut events)) = (result, events.lock()) {
+            events.push(
+                get_sec_elapsed(),
+                &target,
+                EventKind::ProbeError(e.to_string().trim().to_string()),
+            );
+        }
+
+        // A target that can't be resolved makes ping exit right away, so back off instead of
+        // filling the event log with restarts
+        if started.elapsed() > MAX_RESTART_DELAY {
+            restart_delay = MIN_RESTART_DELAY;
+        }
+        tokio::time::sleep(restart_delay).await;
+        restart_delay = (restart_delay * 2).min(MAX_RESTART_DELAY);
+
+        if let Ok(mut events) = events.lock() {
+            events.push(get_sec_elapsed(), &target, EventKind::ProbeRestart);
+        }
+    }
+}
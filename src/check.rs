@@ -0,0 +1,286 @@
+use crate::{init::CheckArgs, oneshot};
+
+use egui_ping_graph::{events::EventKind, ProbeRuntime};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Status {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl Status {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Warning => "WARNING",
+            Self::Critical => "CRITICAL",
+            Self::Unknown => "UNKNOWN",
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Ok => 0,
+            Self::Warning => 1,
+            Self::Critical => 2,
+            Self::Unknown => 3,
+        }
+    }
+}
+
+// Same shape as check_ping thresholds: `<rta ms>,<loss>%` with an optional `,<jitter ms>`
+struct Thresholds {
+    rta_ms: f64,
+    loss: f64,
+    jitter_ms: Option<f64>,
+}
+
+impl Thresholds {
+    fn parse(val: &str) -> Result<Self, String> {
+        let parts: Vec<_> = val.split(',').map(str::trim).collect();
+        let number = |x: &str| {
+            x.trim_end_matches('%').parse::<f64>().map_err(|_| {
+                format!("invalid threshold `{val}`, expected <rta ms>,<loss>%[,<jitter ms>]")
+            })
+        };
+
+        match parts.as_slice() {
+            [rta, loss] => Ok(Self {
+                rta_ms: number(rta)?,
+                loss: number(loss)?,
+                jitter_ms: None,
+            }),
+            [rta, loss, jitter] => Ok(Self {
+                rta_ms: number(rta)?,
+                loss: number(loss)?,
+                jitter_ms: Some(number(jitter)?),
+            }),
+            _ => Err(format!(
+                "invalid threshold `{val}`, expected <rta ms>,<loss>%[,<jitter ms>]"
+            )),
+        }
+    }
+
+    fn exceeded(&self, rta_ms: f64, loss: f64, jitter_ms: f64) -> bool {
+        // No replies leave rta NaN, the loss covers that case
+        // Reaching a threshold counts, like check_ping
+        rta_ms >= self.rta_ms || loss >= self.loss || self.jitter_ms.is_some_and(|x| jitter_ms >= x)
+    }
+}
+
+fn last_probe_error(probes: &ProbeRuntime, target: &str) -> Option<String> {
+    let events = probes.events.lock().ok()?;
+
+    let error = events
+        .iter()
+        .rev()
+        .filter(|x| x.target == target)
+        .find_map(|x| match &x.kind {
+            EventKind::ProbeError(e) => Some(e.clone()),
+            _ => None,
+        });
+    error
+}
+
+fn fmt_perf(val: Option<f64>) -> String {
+    val.map(|x| format!("{x:.3}")).unwrap_or_default()
+}
+
+// Perfdata marks a value that couldn't be measured as U, without a unit
+fn fmt_perf_value(val: Option<f64>, unit: &str) -> String {
+    val.map_or("U".to_string(), |x| format!("{x:.3}{unit}"))
+}
+
+// Prints the plugin output line and returns the status to exit with
+pub fn run(probes: &ProbeRuntime, args: &CheckArgs) -> Status {
+    let thresholds = (
+        Thresholds::parse(&args.warning),
+        Thresholds::parse(&args.critical),
+    );
+    let (warning, critical) = match thresholds {
+        (Ok(warning), Ok(critical)) => (warning, critical),
+        (Err(e), _) | (_, Err(e)) => {
+            println!("PING UNKNOWN - {e}");
+            return Status::Unknown;
+        }
+    };
+
+    let summaries = oneshot::collect(
+        probes,
+        std::slice::from_ref(&args.target),
+        Some(args.count),
+        None,
+    );
+    let Some(summary) = summaries.first() else {
+        println!("PING UNKNOWN - {}: no result", args.target);
+        return Status::Unknown;
+    };
+
+    let (status, output) = report(summary, &warning, &critical, || {
+        last_probe_error(probes, &args.target)
+    });
+    println!("{output}");
+
+    status
+}
+
+// Judges a summary against the thresholds and formats the plugin output line for it
+fn report(
+    summary: &oneshot::Summary,
+    warning: &Thresholds,
+    critical: &Thresholds,
+    probe_error: impl FnOnce() -> Option<String>,
+) -> (Status, String) {
+    let Some(loss) = summary.loss() else {
+        let reason = probe_error().unwrap_or_else(|| "no samples collected".to_string());
+        return (
+            Status::Unknown,
+            format!("PING UNKNOWN - {}: {reason}", summary.target),
+        );
+    };
+
+    let rta_ms = summary.stats.avg * 1000.0;
+    let jitter_ms = summary.stats.jitter * 1000.0;
+
+    let status = if critical.exceeded(rta_ms, loss, jitter_ms) {
+        Status::Critical
+    } else if warning.exceeded(rta_ms, loss, jitter_ms) {
+        Status::Warning
+    } else {
+        Status::Ok
+    };
+
+    let rta = (summary.received > 0).then_some(rta_ms);
+    let jitter = (summary.received > 1).then_some(jitter_ms);
+
+    let rta_text = match rta {
+        Some(rta) => format!("rta {rta:.3}ms"),
+        None => "rta nan".to_string(),
+    };
+
+    let output = format!(
+        "PING {} - {}: {rta_text}, lost {loss:.0}% | rta={};{:.3};{:.3};0; pl={loss:.0}%;{};{};0;100 jitter={};{};{};0;",
+        status.label(),
+        summary.target,
+        fmt_perf_value(rta, "ms"),
+        warning.rta_ms,
+        critical.rta_ms,
+        warning.loss,
+        critical.loss,
+        fmt_perf_value(jitter, "ms"),
+        fmt_perf(warning.jitter_ms),
+        fmt_perf(critical.jitter_ms),
+    );
+
+    (status, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use egui_ping_graph::series::PingStatistics;
+
+    fn summary(sent: usize, received: usize, avg: f64, jitter: f64) -> oneshot::Summary {
+        oneshot::Summary {
+            target: "example.com".to_string(),
+            sent,
+            received,
+            stats: PingStatistics {
+                avg,
+                jitter,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn thresholds() -> (Thresholds, Thresholds) {
+        (
+            Thresholds::parse("100,20%").unwrap(),
+            Thresholds::parse("500, 60%, 50").unwrap(),
+        )
+    }
+
+    #[test]
+    fn parses_thresholds() {
+        let (warning, critical) = thresholds();
+        assert_eq!(
+            (warning.rta_ms, warning.loss, warning.jitter_ms),
+            (100.0, 20.0, None)
+        );
+        assert_eq!(
+            (critical.rta_ms, critical.loss, critical.jitter_ms),
+            (500.0, 60.0, Some(50.0))
+        );
+
+        for invalid in ["", "100", "100,20%,5,1", "fast,20%", "100,,5"] {
+            assert!(Thresholds::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn reaching_a_threshold_exceeds_it() {
+        let (warning, critical) = thresholds();
+
+        assert!(warning.exceeded(100.0, 0.0, 0.0));
+        assert!(warning.exceeded(0.0, 20.0, 0.0));
+        assert!(!warning.exceeded(99.999, 19.9, 1000.0));
+        assert!(critical.exceeded(0.0, 0.0, 50.0));
+        assert!(!critical.exceeded(f64::NAN, 0.0, 49.9));
+    }
+
+    #[test]
+    fn no_samples_is_unknown() {
+        let (warning, critical) = thresholds();
+        let empty = summary(0, 0, f64::NAN, f64::NAN);
+
+        let (status, output) = report(&empty, &warning, &critical, || None);
+        assert!(status == Status::Unknown);
+        assert_eq!(output, "PING UNKNOWN - example.com: no samples collected");
+
+        let (_, output) = report(&empty, &warning, &critical, || {
+            Some("Name or service not known".to_string())
+        });
+        assert_eq!(
+            output,
+            "PING UNKNOWN - example.com: Name or service not known"
+        );
+    }
+
+    #[test]
+    fn formats_perfdata() {
+        let (warning, critical) = thresholds();
+
+        let (status, output) = report(&summary(10, 9, 0.0125, 0.0015), &warning, &critical, || {
+            None
+        });
+        assert!(status == Status::Ok);
+        assert_eq!(
+            output,
+            "PING OK - example.com: rta 12.500ms, lost 10% | rta=12.500ms;100.000;500.000;0; pl=10%;20;60;0;100 jitter=1.500ms;;50.000;0;"
+        );
+
+        let (status, output) = report(&summary(5, 4, 0.2, 0.01), &warning, &critical, || None);
+        assert!(status == Status::Warning);
+        assert!(output.starts_with("PING WARNING - example.com: rta 200.000ms, lost 20% |"));
+
+        // A single reply has no jitter, no reply has no rta either
+        let (status, output) = report(&summary(4, 1, 0.01, f64::NAN), &warning, &critical, || None);
+        assert!(status == Status::Critical);
+        assert!(output.contains(" jitter=U;;50.000;0;"));
+
+        let (status, output) = report(
+            &summary(3, 0, f64::NAN, f64::NAN),
+            &warning,
+            &critical,
+            || None,
+        );
+        assert!(status == Status::Critical);
+        assert_eq!(
+            output,
+            "PING CRITICAL - example.com: rta nan, lost 100% | rta=U;100.000;500.000;0; pl=100%;20;60;0;100 jitter=U;;50.000;0;"
+        );
+    }
+}
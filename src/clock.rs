@@ -0,0 +1,101 @@
+use std::sync::{Arc, Mutex};
+
+use time::OffsetDateTime;
+
+/// Where every series, event and label gets its time from, as seconds since the clock started
+pub trait Clock: Send + Sync {
+    /// Seconds since the clock started
+    fn elapsed(&self) -> f64;
+
+    /// Wall clock time at elapsed zero
+    fn start_datetime(&self) -> OffsetDateTime;
+
+    /// Wall clock time at the given elapsed seconds
+    fn datetime_at(&self, elapsed: f64) -> OffsetDateTime {
+        self.start_datetime() + time::Duration::seconds_f64(elapsed)
+    }
+
+    /// Seconds since the Unix epoch, for passing times between processes
+    fn unix_time_at(&self, elapsed: f64) -> f64 {
+        self.datetime_at(elapsed).unix_timestamp_nanos() as f64 / 1e9
+    }
+
+    /// Inverse of `unix_time_at`
+    fn elapsed_at_unix_time(&self, unix_time: f64) -> f64 {
+        unix_time - self.unix_time_at(0.0)
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Follows the system's monotonic clock from when it was created
+pub struct SystemClock {
+    start: time::Instant,
+    start_datetime: OffsetDateTime,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: time::Instant::now(),
+            start_datetime: OffsetDateTime::now_utc(),
+        }
+    }
+
+    pub fn shared() -> SharedClock {
+        Arc::new(Self::new())
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn elapsed(&self) -> f64 {
+        (time::Instant::now() - self.start).as_seconds_f64()
+    }
+
+    fn start_datetime(&self) -> OffsetDateTime {
+        self.start_datetime
+    }
+}
+
+/// Only moves when told to, for replaying recorded data and for tests
+pub struct ManualClock {
+    start_datetime: OffsetDateTime,
+    elapsed: Mutex<f64>,
+}
+
+impl ManualClock {
+    pub fn new(start_datetime: OffsetDateTime) -> Self {
+        Self {
+            start_datetime,
+            elapsed: Mutex::new(0.0),
+        }
+    }
+
+    pub fn set(&self, elapsed: f64) {
+        if let Ok(mut current) = self.elapsed.lock() {
+            *current = elapsed;
+        }
+    }
+
+    pub fn advance(&self, secs: f64) {
+        if let Ok(mut current) = self.elapsed.lock() {
+            *current += secs;
+        }
+    }
+}
+
+impl Clock for ManualClock {
+    fn elapsed(&self) -> f64 {
+        self.elapsed.lock().map_or(0.0, |x| *x)
+    }
+
+    fn start_datetime(&self) -> OffsetDateTime {
+        self.start_datetime
+    }
+}
@@ -0,0 +1,102 @@
+pub use crate::{
+    agent::AgentConfig,
+    alert::{AlertMetric, AlertRule, Comparison},
+    export::{ExporterConfig, ExporterKind},
+    notify::{SinkConfig, SinkKind},
+};
+
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::{bail, Report, WrapErr};
+use serde::Deserialize;
+
+#[derive(Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(rename = "alert")]
+    pub alerts: Vec<AlertRule>,
+    #[serde(rename = "sink")]
+    pub sinks: Vec<SinkConfig>,
+    #[serde(rename = "exporter")]
+    pub exporters: Vec<ExporterConfig>,
+    #[serde(rename = "agent")]
+    pub agents: Vec<AgentConfig>,
+    // Serves Prometheus metrics on this address when set
+    pub metrics_listen: Option<SocketAddr>,
+    // Serves the JSON control API on this Unix socket when set
+    pub control_socket: Option<PathBuf>,
+    // Serves the JSON control API on this loopback address when set
+    pub control_listen: Option<SocketAddr>,
+}
+
+impl Config {
+    pub fn load(path: Option<&Path>) -> Result<Self, Report> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let content = std::fs::read_to_string(path)
+            .wrap_err_with(|| format!("Failed to read config {}", path.display()))?;
+
+        Self::parse(&content).wrap_err_with(|| format!("Failed to load config {}", path.display()))
+    }
+
+    fn parse(content: &str) -> Result<Self, Report> {
+        let config: Self = toml::from_str(content)?;
+
+        for sink in &config.sinks {
+            sink.validate()?;
+        }
+        for exporter in &config.exporters {
+            exporter.validate()?;
+        }
+
+        let sink_names: Vec<_> = config.sinks.iter().map(|x| x.name.as_str()).collect();
+        for (idx, rule) in config.alerts.iter().enumerate() {
+            rule.validate(&sink_names)?;
+
+            if config.alerts[..idx].iter().any(|x| x.name == rule.name) {
+                bail!("Alert rule name {} is used more than once", rule.name);
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINK: &str = "[[sink]]\nname = \"log\"\nkind = \"file\"\npath = \"/tmp/alerts.log\"\n";
+
+    fn rule(name: &str, sinks: &str) -> String {
+        format!("[[alert]]\nname = \"{name}\"\nmetric = \"loss\"\ncomparison = \"above\"\nsinks = [{sinks}]\n")
+    }
+
+    #[test]
+    fn accepts_rules_routed_to_configured_sinks() {
+        let config =
+            Config::parse(&format!("{SINK}{}{}", rule("a", "\"log\""), rule("b", ""))).unwrap();
+
+        assert_eq!(config.alerts.len(), 2);
+    }
+
+    #[test]
+    fn rejects_unnamed_and_duplicate_rules() {
+        assert!(Config::parse(&rule("", "")).is_err());
+        assert!(Config::parse(&format!("{}{}", rule("a", ""), rule("a", ""))).is_err());
+    }
+
+    #[test]
+    fn rejects_rules_routed_to_unknown_sinks() {
+        let Err(error) = Config::parse(&format!("{SINK}{}", rule("a", "\"pager\""))) else {
+            panic!("unknown sink accepted");
+        };
+
+        assert!(error.to_string().contains("unknown sink pager"));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::{
+    clock::SharedClock,
+    events::{EventKind, EventLog},
+    funcs,
+    probe::TargetManager,
+    series::Series,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// One request per line, e.g. {"cmd": "add", "target": "8.8.8.8"}
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Request {
+    Targets,
+    Add {
+        target: String,
+    },
+    Remove {
+        target: String,
+    },
+    Stats {
+        target: Option<String>,
+    },
+    Samples {
+        target: String,
+        // Only samples from the last this many seconds, everything kept when unset
+        seconds: Option<f64>,
+    },
+    Annotate {
+        text: String,
+        // Shown on every target when unset
+        target: Option<String>,
+    },
+}
+
+// Answers control API requests against the running probes
+pub struct Control {
+    pub clock: SharedClock,
+    pub timeseries: Arc<Mutex<HashMap<String, Series>>>,
+    pub events: Arc<Mutex<EventLog>>,
+    pub targets: Arc<TargetManager>,
+}
+
+impl Control {
+    fn get_sec_elapsed(&self) -> f64 {
+        self.clock.elapsed()
+    }
+
+    // Always answers with a JSON object carrying `ok`, and `error` when it is false
+    pub fn handle_line(&self, line: &str) -> Value {
+        let result = serde_json::from_str(line)
+            .map_err(|e| format!("Invalid request: {e}"))
+            .and_then(|request| self.handle(request));
+
+        match result {
+            Ok(mut response) => {
+                response["ok"] = true.into();
+                response
+            }
+            Err(e) => json!({ "ok": false, "error": e }),
+        }
+    }
+
+    fn handle(&self, request: Request) -> Result<Value, String> {
+        match request {
+            Request::Targets => Ok(json!({ "targets": self.targets.targets() })),
+            Request::Add { target } => Ok(json!({ "added": self.targets.add(&target)? })),
+            Request::Remove { target } => Ok(json!({ "removed": self.targets.remove(&target) })),
+            Request::Stats { target } => {
+                let now = self.get_sec_elapsed();
+                let ts_hash = self.timeseries.lock().map_err(|e| e.to_string())?;
+
+                if let Some(target) = &target {
+                    if !ts_hash.contains_key(target) {
+                        return Err(format!("Unknown target {target}"));
+                    }
+                }
+
+                let mut targets: Vec<_> = ts_hash
+                    .iter()
+                    .filter(|(x, _)| target.as_ref().is_none_or(|target| target == *x))
+                    .collect();
+                targets.sort_by(|a, b| a.0.cmp(b.0));
+
+                let stats: Vec<_> = targets
+                    .into_iter()
+                    .map(|(target, series)| {
+                        funcs::stats_json(self.clock.as_ref(), now, target, series)
+                    })
+                    .collect();
+
+                Ok(json!({ "stats": stats }))
+            }
+            Request::Samples { target, seconds } => {
+                let since = seconds.map_or(f64::NEG_INFINITY, |x| self.get_sec_elapsed() - x);
+                let ts_hash = self.timeseries.lock().map_err(|e| e.to_string())?;
+                let series = ts_hash
+                    .get(&target)
+                    .ok_or_else(|| format!("Unknown target {target}"))?;
+
+                let samples: Vec<_> = series
+                    .samples_younger_than(since)
+                    .iter()
+                    .map(|x| funcs::sample_json(self.clock.as_ref(), &target, x))
+                    .collect();
+
+                Ok(json!({ "samples": samples }))
+            }
+            Request::Annotate { text, target } => {
+                let text = text.trim();
+                if text.is_empty() {
+                    return Err("Annotation text is empty".to_string());
+                }
+
+                let mut events = self.events.lock().map_err(|e| e.to_string())?;
+                events.push(
+                    self.get_sec_elapsed(),
+                    target.as_deref().unwrap_or_default(),
+                    EventKind::Annotation(text.to_string()),
+                );
+
+                Ok(json!({}))
+            }
+        }
+    }
+}
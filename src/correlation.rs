@@ -0,0 +1,223 @@
+use crate::{funcs, series::Series};
+
+use std::collections::HashMap;
+
+const BUCKET_SECS: f64 = 1.0;
+// Buckets at the end of the window that count as "now" for the diagnosis
+const RECENT_BUCKETS: usize = 3;
+
+// A target is degraded when its recent latency exceeds the window median by both margins,
+// or when it is losing packets
+const DEGRADED_RATIO: f64 = 1.5;
+const DEGRADED_MIN_DELTA: f64 = 0.005;
+const DEGRADED_LOSS: f64 = 0.2;
+
+const COMMON_CAUSE_CORRELATION: f64 = 0.5;
+
+#[derive(Clone, PartialEq)]
+pub enum Diagnosis {
+    NoData,
+    Healthy,
+    // Most targets degraded together, likely our own uplink
+    CommonCause(Vec<String>),
+    // Only some paths degraded
+    SinglePath(Vec<String>),
+}
+
+impl Diagnosis {
+    pub fn describe(&self) -> String {
+        match self {
+            Self::NoData => "Not enough data".to_string(),
+            Self::Healthy => "All targets healthy".to_string(),
+            Self::CommonCause(targets) => format!(
+                "Common cause: {} degraded together, likely the local uplink",
+                targets.join(", ")
+            ),
+            Self::SinglePath(targets) => {
+                format!("Single path: only {} degraded", targets.join(", "))
+            }
+        }
+    }
+}
+
+pub struct CorrelationReport {
+    pub targets: Vec<String>,
+    pub latency: Vec<Vec<Option<f64>>>,
+    pub loss: Vec<Vec<Option<f64>>>,
+    pub diagnosis: Diagnosis,
+}
+
+fn correlation_matrix(buckets: &[Vec<Option<f64>>]) -> Vec<Vec<Option<f64>>> {
+    buckets
+        .iter()
+        .map(|a| buckets.iter().map(|b| funcs::pearson(a, b)).collect())
+        .collect()
+}
+
+fn is_degraded(latency: &[Option<f64>], loss: &[Option<f64>]) -> bool {
+    let split = latency.len().saturating_sub(RECENT_BUCKETS);
+
+    let mut baseline: Vec<f64> = latency[..split].iter().filter_map(|&x| x).collect();
+    baseline.sort_by(|a, b| a.total_cmp(b));
+
+    let recent: Vec<f64> = latency[split..].iter().filter_map(|&x| x).collect();
+    let recent_loss: Vec<f64> = loss[split..].iter().filter_map(|&x| x).collect();
+
+    let loss_degraded = !recent_loss.is_empty()
+        && recent_loss.iter().sum::<f64>() / recent_loss.len() as f64 > DEGRADED_LOSS;
+
+    let latency_degraded = match (baseline.get(baseline.len() / 2), recent.is_empty()) {
+        (Some(&median), false) => {
+            let recent_mean = recent.iter().sum::<f64>() / recent.len() as f64;
+            recent_mean > median * DEGRADED_RATIO && recent_mean - median > DEGRADED_MIN_DELTA
+        }
+        _ => false,
+    };
+
+    loss_degraded || latency_degraded
+}
+
+fn mean_correlation(matrix: &[Vec<Option<f64>>], idxs: &[usize]) -> Option<f64> {
+    let values: Vec<f64> = idxs
+        .iter()
+        .enumerate()
+        .flat_map(|(n, &a)| idxs[n + 1..].iter().map(move |&b| (a, b)))
+        .filter_map(|(a, b)| matrix[a][b])
+        .collect();
+
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+pub fn analyze(timeseries: &HashMap<String, Series>, time_range: (f64, f64)) -> CorrelationReport {
+    puffin::profile_function!();
+
+    let mut targets: Vec<_> = timeseries.keys().cloned().collect();
+    targets.sort();
+
+    let time_buckets = (((time_range.1 - time_range.0) / BUCKET_SECS).ceil() as usize).max(1);
+
+    let (latency_buckets, loss_buckets): (Vec<_>, Vec<_>) = targets
+        .iter()
+        .map(|target| {
+            let series = &timeseries[target];
+            let samples: Vec<(f64, f64)> = series
+                .get_younger_than(time_range.0)
+                .iter()
+                .filter_map(|&x| x)
+                .collect();
+
+            (
+                funcs::mean_per_bucket(&samples, time_range, time_buckets),
+                funcs::loss_per_bucket(&samples, &series.timeout_at, time_range, time_buckets),
+            )
+        })
+        .unzip();
+
+    let latency = correlation_matrix(&latency_buckets);
+    let loss = correlation_matrix(&loss_buckets);
+
+    let has_data = latency_buckets
+        .iter()
+        .any(|x| x.iter().filter(|x| x.is_some()).count() > RECENT_BUCKETS);
+
+    let degraded: Vec<usize> = (0..targets.len())
+        .filter(|&i| is_degraded(&latency_buckets[i], &loss_buckets[i]))
+        .collect();
+    let degraded_names = degraded.iter().map(|&i| targets[i].clone()).collect();
+
+    let diagnosis = if !has_data {
+        Diagnosis::NoData
+    } else if degraded.is_empty() {
+        Diagnosis::Healthy
+    } else if degraded.len() >= 2
+        && degraded.len() * 2 >= targets.len()
+        && mean_correlation(&latency, &degraded)
+            .into_iter()
+            .chain(mean_correlation(&loss, &degraded))
+            .any(|x| x >= COMMON_CAUSE_CORRELATION)
+    {
+        Diagnosis::CommonCause(degraded_names)
+    } else {
+        Diagnosis::SinglePath(degraded_names)
+    };
+
+    CorrelationReport {
+        targets,
+        latency,
+        loss,
+        diagnosis,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: (f64, f64) = (0.0, 30.0);
+
+    // One reply a second over RANGE, jumping by `step` for the last few seconds
+    fn series(base: f64, step: f64) -> Series {
+        let mut series = Series::default();
+
+        for idx in 0..30 {
+            let jitter = 0.0003 * (idx % 3) as f64;
+            let rtt = if idx >= 27 { base + step } else { base } + jitter;
+            series.push(Some((idx as f64 + 0.5, rtt)));
+        }
+
+        series
+    }
+
+    fn timeseries(
+        series: impl IntoIterator<Item = (&'static str, Series)>,
+    ) -> HashMap<String, Series> {
+        series
+            .into_iter()
+            .map(|(x, s)| (x.to_string(), s))
+            .collect()
+    }
+
+    #[test]
+    fn all_degrading_together_is_common_cause() {
+        let ts = timeseries([
+            ("a", series(0.010, 0.05)),
+            ("b", series(0.020, 0.05)),
+            ("c", series(0.030, 0.05)),
+        ]);
+
+        let report = analyze(&ts, RANGE);
+
+        assert!(
+            report.diagnosis == Diagnosis::CommonCause(vec!["a".into(), "b".into(), "c".into()])
+        );
+    }
+
+    #[test]
+    fn one_degrading_is_single_path() {
+        let ts = timeseries([
+            ("a", series(0.010, 0.0)),
+            ("b", series(0.020, 0.05)),
+            ("c", series(0.030, 0.0)),
+        ]);
+
+        assert!(analyze(&ts, RANGE).diagnosis == Diagnosis::SinglePath(vec!["b".into()]));
+    }
+
+    #[test]
+    fn steady_targets_are_healthy() {
+        let ts = timeseries([("a", series(0.010, 0.0)), ("b", series(0.020, 0.0))]);
+
+        assert!(analyze(&ts, RANGE).diagnosis == Diagnosis::Healthy);
+    }
+
+    #[test]
+    fn too_few_buckets_is_no_data() {
+        let mut short = Series::default();
+        for idx in 0..RECENT_BUCKETS {
+            short.push(Some((27.5 + idx as f64, 0.1)));
+        }
+        let ts = timeseries([("a", short), ("b", Series::default())]);
+
+        assert!(analyze(&ts, RANGE).diagnosis == Diagnosis::NoData);
+    }
+}
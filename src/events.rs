@@ -0,0 +1,162 @@
+use crate::{
+    alert::AlertTransition,
+    anomaly::{Anomaly, AnomalyKind},
+    clock::{Clock, SharedClock},
+    funcs,
+};
+
+use std::collections::VecDeque;
+
+const MAX_EVENTS: usize = 10_000;
+
+#[derive(Clone)]
+pub enum EventKind {
+    OutageStart,
+    OutageEnd {
+        duration: f64,
+    },
+    ProbeError(String),
+    ProbeRestart,
+    Alert {
+        rule: String,
+        transition: AlertTransition,
+    },
+    Anomaly(Anomaly),
+    Annotation(String),
+}
+
+impl EventKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::OutageStart => "outage start",
+            Self::OutageEnd { .. } => "outage end",
+            Self::ProbeError(_) => "probe error",
+            Self::ProbeRestart => "probe restart",
+            Self::Alert { .. } => "alert",
+            Self::Anomaly(_) => "anomaly",
+            Self::Annotation(_) => "annotation",
+        }
+    }
+
+    pub fn details(&self, target: &str, clock: &dyn Clock) -> String {
+        match self {
+            Self::OutageStart => "consecutive timeouts".to_string(),
+            Self::OutageEnd { duration } => format!("lasted {}", funcs::fmt_float_s(*duration)),
+            Self::ProbeError(e) => e.clone(),
+            Self::ProbeRestart => "".to_string(),
+            Self::Alert { rule, transition } => format!("{rule} {}", transition.label()),
+            Self::Anomaly(anomaly) => anomaly.describe(target, clock),
+            Self::Annotation(text) => text.clone(),
+        }
+    }
+
+    pub fn is_problem(&self) -> bool {
+        match self {
+            Self::OutageStart | Self::ProbeError(_) => true,
+            Self::Alert { transition, .. } => *transition == AlertTransition::Firing,
+            Self::Anomaly(anomaly) => matches!(anomaly.kind, AnomalyKind::LevelShift { .. }),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Event {
+    pub time: f64,
+    pub target: String,
+    pub kind: EventKind,
+}
+
+pub struct EventLog {
+    clock: SharedClock,
+    events: VecDeque<Event>,
+    // Events ever pushed, including ones dropped or cleared since
+    pushed: u64,
+}
+
+fn csv_field(val: &str) -> String {
+    if val.contains([',', '"', '\n']) {
+        format!("\"{}\"", val.replace('"', "\"\""))
+    } else {
+        val.to_string()
+    }
+}
+
+impl EventLog {
+    pub fn new(clock: SharedClock) -> Self {
+        Self {
+            clock,
+            events: VecDeque::new(),
+            pushed: 0,
+        }
+    }
+
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    pub fn push(&mut self, time: f64, target: &str, kind: EventKind) {
+        tracing::info!(
+            "{target}: {} {}",
+            kind.label(),
+            kind.details(target, self.clock())
+        );
+
+        self.events.push_back(Event {
+            time,
+            target: target.to_string(),
+            kind,
+        });
+        self.pushed += 1;
+
+        if self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Event> {
+        self.events.iter()
+    }
+
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    // Events still kept out of those pushed after the first `seen`, events sharing a time are
+    // told apart this way
+    pub fn pushed_after(&self, seen: u64) -> impl Iterator<Item = &Event> {
+        let new = self
+            .pushed
+            .saturating_sub(seen)
+            .min(self.events.len() as u64) as usize;
+        self.events.iter().skip(self.events.len() - new)
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = "time,target,event,details\n".to_string();
+
+        for event in &self.events {
+            out += &format!(
+                "{},{},{},{}\n",
+                funcs::rfc3339_fmt(self.clock(), event.time),
+                csv_field(&event.target),
+                event.kind.label(),
+                csv_field(&event.kind.details(&event.target, self.clock())),
+            );
+        }
+
+        out
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    clock::{Clock, SharedClock},
+    notify,
+    series::Sample,
+};
+
+use std::collections::VecDeque;
+
+use color_eyre::eyre::{Report, WrapErr};
+use serde::Deserialize;
+
+// Keeps datagrams below a typical MTU
+const MAX_DATAGRAM: usize = 1400;
+
+fn default_prefix() -> String {
+    "ping".to_string()
+}
+
+fn default_flush_secs() -> f64 {
+    10.0
+}
+
+fn default_max_buffer() -> usize {
+    100_000
+}
+
+#[derive(Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExporterKind {
+    // Plain http:// only, e.g. http://localhost:8086/write?db=ping with credentials as u/p
+    // query parameters
+    InfluxHttp {
+        url: String,
+    },
+    // UDP is fire-and-forget, nothing tells a lost datagram apart from a delivered one, so
+    // lines that fail to send are dropped instead of buffered
+    InfluxUdp {
+        addr: String,
+    },
+    Statsd {
+        addr: String,
+        #[serde(default = "default_prefix")]
+        prefix: String,
+    },
+}
+
+#[derive(Clone, Deserialize)]
+pub struct ExporterConfig {
+    #[serde(flatten)]
+    pub kind: ExporterKind,
+    #[serde(default = "default_flush_secs")]
+    pub flush_secs: f64,
+    // Lines kept while an HTTP endpoint is unreachable, the oldest get dropped beyond this
+    #[serde(default = "default_max_buffer")]
+    pub max_buffer: usize,
+}
+
+impl ExporterConfig {
+    pub fn validate(&self) -> Result<(), Report> {
+        if let ExporterKind::InfluxHttp { url } = &self.kind {
+            notify::split_http_url(url).wrap_err("Invalid influx exporter")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            ExporterKind::InfluxHttp { url } => format!("influx {url}"),
+            ExporterKind::InfluxUdp { addr } => format!("influx udp://{addr}"),
+            ExporterKind::Statsd { addr, .. } => format!("statsd udp://{addr}"),
+        }
+    }
+}
+
+fn influx_escape(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(' ', "\\ ")
+        .replace('=', "\\=")
+}
+
+pub fn influx_line(clock: &dyn Clock, target: &str, sample: &Sample) -> String {
+    let target = influx_escape(target);
+    let timestamp = clock.datetime_at(sample.time).unix_timestamp_nanos();
+
+    match sample.rtt {
+        Some(rtt) => format!("ping,target={target} rtt={rtt},timeout=false {timestamp}"),
+        None => format!("ping,target={target} timeout=true {timestamp}"),
+    }
+}
+
+pub fn statsd_line(prefix: &str, target: &str, sample: &Sample) -> String {
+    // Dots would split the host into separate metric path segments
+    let target: String = target
+        .chars()
+        .map(|x| {
+            if x.is_ascii_alphanumeric() || x == '-' {
+                x
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    match sample.rtt {
+        Some(rtt) => format!("{prefix}.{target}.rtt:{:.3}|ms", rtt * 1000.0),
+        None => format!("{prefix}.{target}.timeout:1|c"),
+    }
+}
+
+// Joins lines into newline separated payloads of at most MAX_DATAGRAM bytes each, along with
+// the number of lines in each
+pub fn pack_datagrams(lines: &[String]) -> Vec<(String, usize)> {
+    let mut datagrams: Vec<(String, usize)> = vec![];
+
+    for line in lines {
+        match datagrams.last_mut() {
+            Some((datagram, count)) if datagram.len() + 1 + line.len() <= MAX_DATAGRAM => {
+                datagram.push('\n');
+                datagram.push_str(line);
+                *count += 1;
+            }
+            _ => datagrams.push((line.clone(), 1)),
+        }
+    }
+
+    datagrams
+}
+
+pub struct ExportBuffer {
+    pub config: ExporterConfig,
+    clock: SharedClock,
+    lines: VecDeque<String>,
+    pub dropped: u64,
+}
+
+impl ExportBuffer {
+    pub fn new(config: ExporterConfig, clock: SharedClock) -> Self {
+        Self {
+            config,
+            clock,
+            lines: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, target: &str, sample: &Sample) {
+        let line = match &self.config.kind {
+            ExporterKind::InfluxHttp { .. } | ExporterKind::InfluxUdp { .. } => {
+                influx_line(self.clock.as_ref(), target, sample)
+            }
+            ExporterKind::Statsd { prefix, .. } => statsd_line(prefix, target, sample),
+        };
+
+        self.lines.push_back(line);
+
+        while self.lines.len() > self.config.max_buffer {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn peek(&self, count: usize) -> Vec<String> {
+        self.lines.iter().take(count).cloned().collect()
+    }
+
+    // Drops lines once they have been delivered
+    pub fn consume(&mut self, count: usize) {
+        self.lines.drain(..count.min(self.lines.len()));
+    }
+}
@@ -1,13 +1,8 @@
-#[allow(dead_code)]
-pub fn reduce_max_f64(acc: f64, el: f64) -> f64 {
-    if acc > el {
-        acc
-    } else {
-        el
-    }
-}
+use crate::{
+    clock::Clock,
+    series::{Sample, Series},
+};
 
-#[allow(dead_code)]
 pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
     let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
     let hue_prime = hue / 60.0;
@@ -31,6 +26,7 @@ pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (u8, u8, u8) {
     )
 }
 
+/// Seconds as a short human readable duration
 pub fn fmt_float_s(val: f64) -> String {
     if val > 1.0 {
         let val = (val * 100.0).round() / 100.0;
@@ -41,13 +37,51 @@ pub fn fmt_float_s(val: f64) -> String {
     }
 }
 
-pub fn x_axis_fmt(val: f64) -> String {
+/// Parses durations like `30s`, `2m`, `500ms` or plain seconds into seconds
+pub fn parse_duration(val: &str) -> Result<f64, String> {
+    let val = val.trim();
+    let split = val
+        .find(|x: char| !(x.is_ascii_digit() || x == '.'))
+        .unwrap_or(val.len());
+    let (number, unit) = val.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("`{val}` is not a duration"))?;
+
+    let scale = match unit.trim() {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        unit => return Err(format!("Unknown duration unit `{unit}`, use ms, s, m or h")),
+    };
+
+    Ok(number * scale)
+}
+
+pub fn rfc3339_fmt(clock: &dyn Clock, val: f64) -> String {
+    let dt = clock.datetime_at(val);
+
+    format!(
+        "{:0>4}-{:0>2}-{:0>2}T{:0>2}:{:0>2}:{:0>2}.{:0>3}Z",
+        dt.year(),
+        dt.month() as u8,
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second(),
+        dt.millisecond()
+    )
+}
+
+/// Wall clock time of the given elapsed seconds as `HH:MM:SS`
+pub fn x_axis_fmt(clock: &dyn Clock, val: f64) -> String {
     if val.is_sign_negative() {
         return "".into();
     }
 
-    let datetime_since_start = *crate::statics::DATETIME_START.get().unwrap();
-    let asd = datetime_since_start + time::Duration::seconds_f64(val);
+    let asd = clock.datetime_at(val);
 
     format!(
         "{:0>2}:{:0>2}:{:0>2}",
@@ -57,11 +91,12 @@ pub fn x_axis_fmt(val: f64) -> String {
     )
 }
 
+/// Latency axis label
 pub fn y_axis_fmt(val: f64) -> String {
     fmt_float_s(val)
 }
 
-pub fn xy_label_fmt(serieslabel: &str, point: &egui_plot::PlotPoint) -> String {
+pub fn xy_label_fmt(clock: &dyn Clock, serieslabel: &str, point: &egui_plot::PlotPoint) -> String {
     let latency_f = point.y;
     let time_f = point.x;
 
@@ -75,7 +110,7 @@ pub fn xy_label_fmt(serieslabel: &str, point: &egui_plot::PlotPoint) -> String {
         format!(
             "Latency: {latency}\nTime: {t}",
             latency = y_axis_fmt(latency_f),
-            t = x_axis_fmt(time_f),
+            t = x_axis_fmt(clock, time_f),
         )
     } else {
         "".to_string()
@@ -84,6 +119,7 @@ pub fn xy_label_fmt(serieslabel: &str, point: &egui_plot::PlotPoint) -> String {
     host_label + &lat_time
 }
 
+/// Statistics over the replies of a series, gaps count as timeouts
 pub fn pingstat_from_rawdata(rawdata: &[Option<(f64, f64)>]) -> crate::series::PingStatistics {
     use statrs::statistics::*;
 
@@ -103,3 +139,249 @@ pub fn pingstat_from_rawdata(rawdata: &[Option<(f64, f64)>]) -> crate::series::P
         timeouts,
     }
 }
+
+// Returns (bucket_start, fraction_of_samples) for every non-empty bucket, buckets are
+// aligned to multiples of bucket_width so negative (log scaled) values work too
+pub fn histogram_from_values(values: &[f64], bucket_width: f64) -> Vec<(f64, f64)> {
+    use std::collections::BTreeMap;
+
+    if values.is_empty() || bucket_width <= 0.0 {
+        return vec![];
+    }
+
+    let mut buckets: BTreeMap<i64, u32> = BTreeMap::new();
+    for val in values {
+        *buckets
+            .entry((val / bucket_width).floor() as i64)
+            .or_default() += 1;
+    }
+
+    let total = values.len() as f64;
+    buckets
+        .into_iter()
+        .map(|(idx, count)| (idx as f64 * bucket_width, count as f64 / total))
+        .collect()
+}
+
+pub fn cdf_from_values(values: &[f64]) -> Vec<(f64, f64)> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let total = sorted.len() as f64;
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(i, val)| (val, (i + 1) as f64 / total))
+        .collect()
+}
+
+// Returns 0 for no samples so it can be used directly as a plot bound
+pub fn percentile(values: &[f64], percentile: f64) -> f64 {
+    use statrs::statistics::*;
+
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    Data::new(values.to_vec()).quantile(percentile / 100.0)
+}
+
+// Counts samples into a grid indexed as grid[time_bucket][latency_bucket], latencies above
+// y_max are clamped into the top bucket
+pub fn heatmap_from_samples(
+    samples: &[(f64, f64)],
+    time_range: (f64, f64),
+    time_buckets: usize,
+    y_max: f64,
+    latency_buckets: usize,
+) -> Vec<Vec<u32>> {
+    let mut grid = vec![vec![0u32; latency_buckets]; time_buckets];
+    let (t_start, t_end) = time_range;
+
+    if t_end <= t_start || y_max <= 0.0 || latency_buckets == 0 {
+        return grid;
+    }
+
+    for &(t, y) in samples {
+        if t < t_start || t >= t_end {
+            continue;
+        }
+
+        let t_idx = ((t - t_start) / (t_end - t_start) * time_buckets as f64) as usize;
+        let y_idx = ((y / y_max) * latency_buckets as f64) as usize;
+
+        grid[t_idx.min(time_buckets - 1)][y_idx.min(latency_buckets - 1)] += 1;
+    }
+
+    grid
+}
+
+// Fraction of probes that timed out per time bucket, None for buckets without any probe
+pub fn loss_per_bucket(
+    samples: &[(f64, f64)],
+    timeouts: &[f64],
+    time_range: (f64, f64),
+    time_buckets: usize,
+) -> Vec<Option<f64>> {
+    let (t_start, t_end) = time_range;
+    let mut counts = vec![(0u32, 0u32); time_buckets];
+
+    if t_end <= t_start {
+        return vec![None; time_buckets];
+    }
+
+    let to_idx = |t: f64| {
+        if t < t_start || t >= t_end {
+            None
+        } else {
+            let idx = ((t - t_start) / (t_end - t_start) * time_buckets as f64) as usize;
+            Some(idx.min(time_buckets - 1))
+        }
+    };
+
+    for &(t, _) in samples {
+        if let Some(idx) = to_idx(t) {
+            counts[idx].0 += 1;
+        }
+    }
+
+    for &t in timeouts {
+        if let Some(idx) = to_idx(t) {
+            counts[idx].1 += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(ok, lost)| match ok + lost {
+            0 => None,
+            total => Some(lost as f64 / total as f64),
+        })
+        .collect()
+}
+
+// Mean latency per time bucket, None for buckets without samples
+pub fn mean_per_bucket(
+    samples: &[(f64, f64)],
+    time_range: (f64, f64),
+    time_buckets: usize,
+) -> Vec<Option<f64>> {
+    let (t_start, t_end) = time_range;
+    let mut sums = vec![(0.0, 0u32); time_buckets];
+
+    if t_end <= t_start {
+        return vec![None; time_buckets];
+    }
+
+    for &(t, y) in samples {
+        if t < t_start || t >= t_end {
+            continue;
+        }
+
+        let idx = ((t - t_start) / (t_end - t_start) * time_buckets as f64) as usize;
+        let bucket = &mut sums[idx.min(time_buckets - 1)];
+        bucket.0 += y;
+        bucket.1 += 1;
+    }
+
+    sums.into_iter()
+        .map(|(sum, count)| (count > 0).then(|| sum / count as f64))
+        .collect()
+}
+
+// Pearson correlation over the positions where both series have a value
+pub fn pearson(a: &[Option<f64>], b: &[Option<f64>]) -> Option<f64> {
+    let pairs: Vec<(f64, f64)> = a
+        .iter()
+        .zip(b.iter())
+        .filter_map(|(&x, &y)| Some((x?, y?)))
+        .collect();
+
+    if pairs.len() < 3 {
+        return None;
+    }
+
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|x| x.0).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|x| x.1).sum::<f64>() / n;
+
+    let (cov, var_x, var_y) = pairs.iter().fold((0.0, 0.0, 0.0), |acc, &(x, y)| {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        (acc.0 + dx * dy, acc.1 + dx * dx, acc.2 + dy * dy)
+    });
+
+    if var_x <= f64::EPSILON || var_y <= f64::EPSILON {
+        return None;
+    }
+
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// One sample as the JSON object `--headless` and the control API print
+pub fn sample_json(clock: &dyn Clock, target: &str, sample: &Sample) -> serde_json::Value {
+    serde_json::json!({
+        "type": "sample",
+        "time": rfc3339_fmt(clock, sample.time),
+        "target": target,
+        "rtt": sample.rtt,
+        "timeout": sample.rtt.is_none(),
+    })
+}
+
+/// Statistics of a target as the JSON object `--headless` and the control API print
+pub fn stats_json(
+    clock: &dyn Clock,
+    time: f64,
+    target: &str,
+    series: &Series,
+) -> serde_json::Value {
+    let stats = &series.stats;
+    // Statistics of an empty window come out as NaN or infinite
+    let finite = |x: f64| Some(x).filter(|x| x.is_finite());
+
+    serde_json::json!({
+        "type": "stats",
+        "time": rfc3339_fmt(clock, time),
+        "target": target,
+        "last": finite(stats.last),
+        "min": finite(stats.min),
+        "max": finite(stats.max),
+        "avg": finite(stats.avg),
+        "jitter": finite(stats.jitter),
+        "p95": finite(stats.p95),
+        "timeouts": stats.timeouts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn clock() -> ManualClock {
+        // 2024-01-01T23:59:58Z, close enough to midnight to roll the date over
+        ManualClock::new(time::OffsetDateTime::from_unix_timestamp(1_704_153_598).unwrap())
+    }
+
+    #[test]
+    fn x_axis_follows_clock_start() {
+        let clock = clock();
+
+        assert_eq!(x_axis_fmt(&clock, 0.0), "23:59:58");
+        assert_eq!(x_axis_fmt(&clock, 2.5), "00:00:00");
+        assert_eq!(x_axis_fmt(&clock, 3661.0), "01:00:59");
+        assert_eq!(x_axis_fmt(&clock, -1.0), "");
+    }
+
+    #[test]
+    fn rfc3339_rolls_over_the_date() {
+        let clock = clock();
+
+        assert_eq!(rfc3339_fmt(&clock, 0.0), "2024-01-01T23:59:58.000Z");
+        assert_eq!(rfc3339_fmt(&clock, 2.25), "2024-01-02T00:00:00.250Z");
+
+        // Formatting goes by the sample's time, not by where the clock is now
+        clock.advance(100.0);
+        assert_eq!(rfc3339_fmt(&clock, 1.5), "2024-01-01T23:59:59.500Z");
+    }
+}
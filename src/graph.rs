@@ -0,0 +1,838 @@
+pub use crate::{
+    smoothing::{SmoothingFilter, SmoothingSettings},
+    yaxis::{YAxisMode, YAxisOptions},
+};
+
+use crate::{
+    anomaly,
+    clock::{SharedClock, SystemClock},
+    events, funcs,
+    probe::ProbeRuntime,
+    series::Series,
+    smoothing, style, yaxis,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+const MIN_SUBPLOT_HEIGHT: f32 = 80.0;
+const LOSS_STRIP_HEIGHT: f32 = 70.0;
+const LOSS_STRIP_BUCKETS: usize = 60;
+
+// Timeouts closer together than this are drawn as one shaded band
+const TIMEOUT_BAND_GAP: f64 = 1.0;
+// Fraction of the plot height covered by a single timeout tick
+const TIMEOUT_TICK_HEIGHT: f64 = 0.08;
+
+const DEFAULT_FRAME_INTERVAL: std::time::Duration = std::time::Duration::from_millis(33);
+
+/// How several targets share the time plot
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TimeLayout {
+    Overlay,
+    SmallMultiples,
+}
+
+/// Live latency plot with its Details grid, drawing whatever the attached probes collect
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct PingGraph {
+    // Every plot, grid and axis link group is derived from this, so graphs don't share state
+    #[serde(skip)]
+    id_source: egui::Id,
+    #[serde(skip)]
+    clock: SharedClock,
+    #[serde(skip)]
+    timeseries_hash: Arc<Mutex<HashMap<String, Series>>>,
+    #[serde(skip)]
+    alerts: Arc<Mutex<crate::alert::AlertEngine>>,
+    #[serde(skip)]
+    events: Arc<Mutex<events::EventLog>>,
+
+    pub target_styles: HashMap<String, style::TargetStyle>,
+    pub history_window: f64,
+    pub top_padding: f64,
+    pub time_layout: TimeLayout,
+    pub shared_y: bool,
+    pub yaxis: yaxis::YAxisOptions,
+    pub smoothing: HashMap<String, smoothing::SmoothingSettings>,
+    pub show_anomalies: bool,
+    pub show_timeouts: bool,
+    pub show_loss_strip: bool,
+
+    #[serde(skip)]
+    pub follow_live: bool,
+    // Time range the plot was left at while not following live data
+    #[serde(skip)]
+    paused_range: (f64, f64),
+    // Requests the plot to pause and center on this time
+    #[serde(skip)]
+    plot_focus: Option<f64>,
+    #[serde(skip)]
+    pub frame_interval: std::time::Duration,
+
+    // Worked out once per frame by `prepare`
+    #[serde(skip)]
+    f_elapsed: f64,
+    #[serde(skip)]
+    y_range: (f64, f64),
+    #[serde(skip)]
+    jump_to_range: bool,
+    // Frame `prepare` last ran in
+    #[serde(skip)]
+    prepared_frame: Option<u64>,
+}
+
+impl Default for PingGraph {
+    fn default() -> Self {
+        let clock = SystemClock::shared();
+
+        Self {
+            id_source: egui::Id::new("ping_graph"),
+            clock: clock.clone(),
+            timeseries_hash: Arc::new(Mutex::new(HashMap::new())),
+            alerts: Arc::new(Mutex::new(Default::default())),
+            events: Arc::new(Mutex::new(events::EventLog::new(clock))),
+
+            target_styles: HashMap::new(),
+            history_window: 10.0,
+            top_padding: 0.01,
+            time_layout: TimeLayout::Overlay,
+            shared_y: false,
+            yaxis: Default::default(),
+            smoothing: HashMap::new(),
+            show_anomalies: true,
+            show_timeouts: true,
+            show_loss_strip: false,
+
+            follow_live: true,
+            paused_range: (0.0, 0.0),
+            plot_focus: None,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+
+            f_elapsed: 0.0,
+            y_range: (0.0, 0.0),
+            jump_to_range: false,
+            prepared_frame: None,
+        }
+    }
+}
+
+impl PingGraph {
+    /// Graph with default settings showing the probes' targets
+    pub fn new(probes: &ProbeRuntime) -> Self {
+        let mut graph = Self::default();
+        graph.attach(probes);
+        graph
+    }
+
+    /// Needed for every graph after the first one shown in the same app
+    pub fn id_source(mut self, id_source: impl std::hash::Hash) -> Self {
+        self.id_source = egui::Id::new(id_source);
+        self
+    }
+
+    /// Points the graph at the probes' data, needed again after loading saved settings
+    pub fn attach(&mut self, probes: &ProbeRuntime) {
+        self.clock = probes.clock();
+        self.timeseries_hash = probes.timeseries.clone();
+        self.alerts = probes.alerts.clone();
+        self.events = probes.events.clone();
+    }
+
+    pub(crate) fn timeseries(&self) -> &Arc<Mutex<HashMap<String, Series>>> {
+        &self.timeseries_hash
+    }
+
+    pub(crate) fn alerts(&self) -> &Arc<Mutex<crate::alert::AlertEngine>> {
+        &self.alerts
+    }
+
+    pub(crate) fn events(&self) -> &Arc<Mutex<events::EventLog>> {
+        &self.events
+    }
+
+    /// Clock of the attached probes, plot x values are seconds since it started
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Pauses the plot centered on this time
+    pub fn focus(&mut self, time: f64) {
+        self.plot_focus = Some(time);
+        self.prepared_frame = None;
+    }
+
+    /// Time range shown in this frame
+    pub fn visible_range(&mut self, ctx: &egui::Context) -> (f64, f64) {
+        self.prepare(ctx);
+        self.paused_range
+    }
+
+    // Updates styles, the visible time range and the y range, once per frame
+    fn prepare(&mut self, ctx: &egui::Context) {
+        if self.prepared_frame == Some(ctx.frame_nr()) {
+            return;
+        }
+        self.prepared_frame = Some(ctx.frame_nr());
+
+        self.f_elapsed = self.get_sec_since_start();
+
+        if let Ok(ts_hash) = self.timeseries_hash.lock() {
+            for (target, series) in ts_hash.iter() {
+                self.target_styles.entry(target.clone()).or_insert_with(|| {
+                    style::TargetStyle::with_color(style::palette_color(series.linecol_idx))
+                });
+            }
+        }
+
+        let live_range = (
+            self.f_elapsed - crate::DEFAULT_OFFSET - self.history_window,
+            self.f_elapsed - crate::DEFAULT_OFFSET,
+        );
+
+        self.jump_to_range = if let Some(focus) = self.plot_focus.take() {
+            self.follow_live = false;
+            self.paused_range = (
+                focus - self.history_window / 2.0,
+                focus + self.history_window / 2.0,
+            );
+            true
+        } else {
+            false
+        };
+
+        if self.follow_live {
+            self.paused_range = live_range;
+        }
+
+        let visible_range = self.paused_range;
+        self.y_range = {
+            let values: Vec<_> = match self.timeseries_hash.lock() {
+                Ok(ts_hash) => ts_hash
+                    .iter()
+                    .filter(|(target, _)| {
+                        self.target_styles.get(*target).is_some_and(|x| x.visible)
+                    })
+                    .flat_map(|(_, series)| series.values_younger_than(visible_range.0))
+                    .collect(),
+                Err(_) => vec![],
+            };
+            self.yaxis.range(&values, self.top_padding)
+        };
+    }
+
+    /// Details grid and plot at once, keeping the plot scrolling while it follows live data
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        self.show_details(ui);
+        self.show_plot(ui);
+        self.request_repaint(ui.ctx());
+    }
+
+    /// Keeps the time axis scrolling while following live data, new samples wake the UI on
+    /// their own
+    pub fn request_repaint(&self, ctx: &egui::Context) {
+        if self.follow_live {
+            ctx.request_repaint_after(self.frame_interval);
+        }
+    }
+
+    /// Latency plot in the chosen time layout
+    pub fn show_plot(&mut self, ui: &mut egui::Ui) {
+        self.prepare(ui.ctx());
+        self.show_time_plot(
+            ui,
+            self.f_elapsed,
+            self.paused_range,
+            self.y_range,
+            self.jump_to_range,
+        );
+    }
+
+    /// Collapsible grid with the statistics and firing alerts of each target
+    pub fn show_details(&mut self, ui: &mut egui::Ui) {
+        self.prepare(ui.ctx());
+        let Ok(asdf) = self.timeseries_hash.lock() else {
+            return;
+        };
+
+        egui::CollapsingHeader::new("Details")
+            .id_source(self.id_source.with("details"))
+            .default_open(true)
+            .show_unindented(ui, |ui| {
+                egui::Grid::new(self.id_source.with("measurements")).show(ui, |ui| {
+                    let alerts = self.alerts.lock().ok();
+
+                    for (target, series) in asdf.iter() {
+                        let stats = &series.stats;
+                        let firing = alerts
+                            .as_ref()
+                            .map(|x| x.firing_for(target).join(", "))
+                            .unwrap_or_default();
+
+                        if firing.is_empty() {
+                            ui.label(target);
+                        } else {
+                            ui.colored_label(egui::Color32::RED, target);
+                        }
+
+                        ui.label(format!("last: {}", funcs::fmt_float_s(stats.last)));
+                        ui.label(format!("min: {}", funcs::fmt_float_s(stats.min)));
+                        ui.label(format!("max: {}", funcs::fmt_float_s(stats.max)));
+                        ui.label(format!("avg: {}", funcs::fmt_float_s(stats.avg)));
+                        ui.label(format!("jtr: {}", funcs::fmt_float_s(stats.jitter)));
+                        ui.label(format!("p95: {}", funcs::fmt_float_s(stats.p95)));
+                        ui.label(format!("t/0: {}", stats.timeouts));
+
+                        if !firing.is_empty() {
+                            ui.colored_label(egui::Color32::RED, format!("⚠ {firing}"));
+                        }
+                        ui.end_row();
+                    }
+                })
+            });
+    }
+}
+
+impl PingGraph {
+    fn get_sec_since_start(&self) -> f64 {
+        self.clock.elapsed()
+    }
+
+    pub(crate) fn get_highest_value_youngerthan(&self, time: f64) -> f64 {
+        if let Ok(values) = self.timeseries_hash.clone().lock() {
+            values
+                .iter()
+                .map(|x| x.1)
+                .map(|x| x.get_highest_value_youngerthan(time))
+                .max_by(|a, b| a.total_cmp(b))
+                .unwrap_or(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn draw_alerts(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: &str,
+        f_elapsed: f64,
+        y_range: (f64, f64),
+    ) {
+        let Ok(alerts) = self.alerts.lock() else {
+            return;
+        };
+
+        let bottom = self.yaxis.mode.project(y_range.0);
+        let top = self.yaxis.mode.project(y_range.1);
+
+        for interval in alerts.intervals.iter().filter(|x| x.target == target) {
+            let end = interval.end.unwrap_or(f_elapsed);
+
+            plot_ui.polygon(
+                egui_plot::Polygon::new(vec![
+                    [interval.start, bottom],
+                    [end, bottom],
+                    [end, top],
+                    [interval.start, top],
+                ])
+                .fill_color(egui::Color32::from_rgba_unmultiplied(0xB7, 0x48, 0x4D, 40))
+                .stroke(egui::Stroke::NONE)
+                .name(format!("⚠ {}", interval.rule)),
+            );
+        }
+    }
+
+    fn draw_anomalies(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: &str,
+        series: &Series,
+        y_range: (f64, f64),
+    ) {
+        use anomaly::AnomalyKind;
+
+        let color = self.target_styles[target].color;
+
+        for anomaly in &series.anomalies {
+            let (style, label) = match anomaly.kind {
+                AnomalyKind::LevelShift { before, after } => (
+                    egui_plot::LineStyle::dashed_loose(),
+                    format!(
+                        "{} → {}",
+                        funcs::fmt_float_s(before),
+                        funcs::fmt_float_s(after)
+                    ),
+                ),
+                AnomalyKind::Spike { value, .. } => (
+                    egui_plot::LineStyle::dotted_loose(),
+                    funcs::fmt_float_s(value),
+                ),
+            };
+
+            plot_ui.vline(
+                egui_plot::VLine::new(anomaly.time)
+                    .color(color)
+                    .style(style)
+                    .width(1.5),
+            );
+            plot_ui.text(
+                egui_plot::Text::new(
+                    egui_plot::PlotPoint::new(anomaly.time, self.yaxis.mode.project(y_range.1)),
+                    label,
+                )
+                .anchor(egui::Align2::LEFT_TOP)
+                .color(color),
+            );
+        }
+    }
+
+    // Annotations of the given target and those without one, all of them when None
+    fn draw_annotations(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: Option<&str>,
+        y_range: (f64, f64),
+    ) {
+        let Ok(events) = self.events.lock() else {
+            return;
+        };
+
+        for event in events.iter() {
+            let events::EventKind::Annotation(text) = &event.kind else {
+                continue;
+            };
+            if !event.target.is_empty() && target.is_some_and(|x| x != event.target) {
+                continue;
+            }
+
+            let color = plot_ui.ctx().style().visuals.text_color();
+
+            plot_ui.vline(
+                egui_plot::VLine::new(event.time)
+                    .color(color)
+                    .style(egui_plot::LineStyle::dashed_dense())
+                    .width(1.0),
+            );
+            plot_ui.text(
+                egui_plot::Text::new(
+                    egui_plot::PlotPoint::new(event.time, self.yaxis.mode.project(y_range.1)),
+                    format!("✏ {text}"),
+                )
+                .anchor(egui::Align2::LEFT_TOP)
+                .color(color),
+            );
+        }
+    }
+
+    fn draw_timeouts(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: &str,
+        series: &Series,
+        y_range: (f64, f64),
+    ) {
+        if series.timeout_at.is_empty() {
+            return;
+        }
+
+        let bottom = self.yaxis.mode.project(y_range.0);
+        let top = self.yaxis.mode.project(y_range.1);
+        let name = format!("{target} timeouts");
+
+        let ticks = series
+            .timeout_at
+            .iter()
+            .map(|&t| {
+                egui_plot::Bar::new(t, (top - bottom) * TIMEOUT_TICK_HEIGHT)
+                    .base_offset(bottom)
+                    .width(0.05)
+            })
+            .collect();
+        plot_ui.bar_chart(
+            egui_plot::BarChart::new(ticks)
+                .color(egui::Color32::RED)
+                .name(&name),
+        );
+
+        // Shade runs of consecutive timeouts
+        let mut runs: Vec<(f64, f64, usize)> = vec![];
+        for &t in &series.timeout_at {
+            match runs.last_mut() {
+                Some(run) if t - run.1 <= TIMEOUT_BAND_GAP => {
+                    run.1 = t;
+                    run.2 += 1;
+                }
+                _ => runs.push((t, t, 1)),
+            }
+        }
+
+        for (start, end, _) in runs.into_iter().filter(|x| x.2 > 1) {
+            plot_ui.polygon(
+                egui_plot::Polygon::new(vec![
+                    [start, bottom],
+                    [end, bottom],
+                    [end, top],
+                    [start, top],
+                ])
+                .fill_color(egui::Color32::from_rgba_unmultiplied(0xFF, 0x00, 0x00, 30))
+                .stroke(egui::Stroke::NONE)
+                .name(&name),
+            );
+        }
+    }
+
+    fn show_loss_strip(&self, ui: &mut egui::Ui, visible_range: (f64, f64), jump_to_range: bool) {
+        let Ok(ts_hash) = self.timeseries_hash.lock() else {
+            return;
+        };
+
+        let bucket_width = (visible_range.1 - visible_range.0) / LOSS_STRIP_BUCKETS as f64;
+
+        let clock = self.clock.clone();
+
+        self.time_plot(self.id_source.with("loss_strip"))
+            .height(LOSS_STRIP_HEIGHT)
+            .y_axis_formatter(|val, _, _| format!("{val:.0}%"))
+            .label_formatter(move |name, point| {
+                format!(
+                    "Host: {name}\nLoss: {:.1}%\nTime: {}",
+                    point.y,
+                    funcs::x_axis_fmt(clock.as_ref(), point.x)
+                )
+            })
+            .y_axis_label("Loss")
+            .show(ui, |plot_ui| {
+                for (target, series) in ts_hash.iter() {
+                    let Some(style) = self.target_styles.get(target).filter(|x| x.visible) else {
+                        continue;
+                    };
+
+                    let samples: Vec<(f64, f64)> = series
+                        .get_younger_than(visible_range.0)
+                        .iter()
+                        .filter_map(|&x| x)
+                        .collect();
+
+                    let points: Vec<_> = funcs::loss_per_bucket(
+                        &samples,
+                        &series.timeout_at,
+                        visible_range,
+                        LOSS_STRIP_BUCKETS,
+                    )
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(idx, loss)| {
+                        let t = visible_range.0 + (idx as f64 + 0.5) * bucket_width;
+                        loss.map(|loss| [t, loss * 100.0])
+                    })
+                    .collect();
+
+                    plot_ui.line(
+                        egui_plot::Line::new(points)
+                            .color(style.color)
+                            .width((style.width / 2.0).max(1.0))
+                            .name(target),
+                    );
+                }
+
+                if self.follow_live || jump_to_range {
+                    plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
+                        [visible_range.0, 0.0],
+                        [visible_range.1, 100.0],
+                    ));
+                }
+            });
+    }
+
+    // Keeps the time axes of this graph's plots together
+    fn time_link_group(&self) -> egui::Id {
+        self.id_source.with("time_plots")
+    }
+
+    fn time_plot(&self, id: impl std::hash::Hash) -> egui_plot::Plot {
+        let interactive = !self.follow_live;
+        let mode = self.yaxis.mode;
+        let (axis_clock, label_clock) = (self.clock.clone(), self.clock.clone());
+
+        let plot = egui_plot::Plot::new(id)
+            .allow_boxed_zoom(interactive)
+            .allow_double_click_reset(false)
+            .allow_drag(interactive)
+            .allow_scroll(interactive)
+            .allow_zoom(interactive)
+            .link_axis(self.time_link_group(), true, false)
+            .link_cursor(self.time_link_group(), true, false)
+            .x_axis_formatter(move |val, _, _| funcs::x_axis_fmt(axis_clock.as_ref(), val))
+            .y_axis_formatter(move |val, _, _| funcs::y_axis_fmt(mode.unproject(val)))
+            .label_formatter(move |name, point| {
+                funcs::xy_label_fmt(
+                    label_clock.as_ref(),
+                    name,
+                    &egui_plot::PlotPoint::new(point.x, mode.unproject(point.y)),
+                )
+            });
+
+        if mode == YAxisMode::Log {
+            plot.y_grid_spacer(yaxis::log_grid_marks)
+        } else {
+            plot
+        }
+    }
+
+    fn draw_offscale(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: &str,
+        series: &Series,
+        color: egui::Color32,
+        y_range: (f64, f64),
+    ) {
+        let (mut above, mut below) = (vec![], vec![]);
+
+        for &(x, y) in series.raw.iter().flatten() {
+            if y > y_range.1 {
+                above.push([x, y_range.1]);
+            } else if y < y_range.0 {
+                below.push([x, y_range.0]);
+            }
+        }
+
+        for (points, shape) in [
+            (above, egui_plot::MarkerShape::Up),
+            (below, egui_plot::MarkerShape::Down),
+        ] {
+            if points.is_empty() {
+                continue;
+            }
+
+            plot_ui.points(
+                egui_plot::Points::new(points)
+                    .shape(shape)
+                    .filled(true)
+                    .radius(4.0)
+                    .color(color)
+                    .name(format!("{target} (off-scale)")),
+            );
+        }
+    }
+
+    fn draw_target(
+        &self,
+        plot_ui: &mut egui_plot::PlotUi,
+        target: &str,
+        series: &Series,
+        f_elapsed: f64,
+        y_range: (f64, f64),
+    ) {
+        puffin::profile_scope!("series_iter", target);
+
+        let Some(style) = self.target_styles.get(target).filter(|x| x.visible) else {
+            return;
+        };
+
+        let mode = self.yaxis.mode;
+
+        for points in series.splitted_to_plotpoints(|y| mode.project(y)) {
+            puffin::profile_scope!("lines_iter");
+            plot_ui.line(
+                egui_plot::Line::new(points)
+                    .color(style.color)
+                    .width(style.width)
+                    .style(style.dash.line_style())
+                    .fill(mode.project(y_range.0) as f32)
+                    .name(target),
+            )
+        }
+
+        if mode.clips() {
+            self.draw_offscale(plot_ui, target, series, style.color, y_range);
+        }
+
+        self.draw_alerts(plot_ui, target, f_elapsed, y_range);
+
+        if self.show_timeouts {
+            self.draw_timeouts(plot_ui, target, series, y_range);
+        }
+
+        if self.show_anomalies {
+            self.draw_anomalies(plot_ui, target, series, y_range);
+        }
+
+        let Some(settings) = self.smoothing.get(target).filter(|x| x.enabled) else {
+            return;
+        };
+
+        puffin::profile_scope!("smoothing_iter");
+        for segment in series.get_splitted() {
+            let points: Vec<_> = smoothing::smooth(settings, &segment)
+                .into_iter()
+                .map(|(x, y)| [x, mode.project(y)])
+                .collect();
+
+            plot_ui.line(
+                egui_plot::Line::new(points)
+                    .color(style.color)
+                    .width((style.width / 2.0).max(1.0))
+                    .name(format!("{target} ({})", settings.filter.label())),
+            )
+        }
+    }
+
+    fn time_plot_bounds(
+        &self,
+        visible_range: (f64, f64),
+        y_range: (f64, f64),
+    ) -> egui_plot::PlotBounds {
+        egui_plot::PlotBounds::from_min_max(
+            [visible_range.0, self.yaxis.mode.project(y_range.0)],
+            [visible_range.1, self.yaxis.mode.project(y_range.1)],
+        )
+    }
+
+    fn handle_time_plot_response(&mut self, double_clicked: bool, bounds: egui_plot::PlotBounds) {
+        if double_clicked {
+            self.follow_live = true;
+        } else if !self.follow_live {
+            self.paused_range = (bounds.min()[0], bounds.max()[0]);
+        }
+    }
+
+    fn show_time_plot(
+        &mut self,
+        ui: &mut egui::Ui,
+        f_elapsed: f64,
+        visible_range: (f64, f64),
+        y_range: (f64, f64),
+        jump_to_range: bool,
+    ) {
+        let strip_height = if self.show_loss_strip {
+            LOSS_STRIP_HEIGHT + ui.spacing().item_spacing.y
+        } else {
+            0.0
+        };
+        let plot_size = egui::vec2(ui.available_width(), ui.available_height() - strip_height);
+
+        ui.allocate_ui(plot_size, |ui| match self.time_layout {
+            TimeLayout::Overlay => {
+                self.show_overlay_plot(ui, f_elapsed, visible_range, y_range, jump_to_range)
+            }
+            TimeLayout::SmallMultiples => {
+                self.show_small_multiples(ui, f_elapsed, visible_range, y_range, jump_to_range)
+            }
+        });
+
+        if self.show_loss_strip {
+            self.show_loss_strip(ui, visible_range, jump_to_range);
+        }
+    }
+
+    fn show_overlay_plot(
+        &mut self,
+        ui: &mut egui::Ui,
+        f_elapsed: f64,
+        visible_range: (f64, f64),
+        y_range: (f64, f64),
+        jump_to_range: bool,
+    ) {
+        let response = self
+            .time_plot(self.id_source.with("plot"))
+            .legend(egui_plot::Legend::default())
+            .x_axis_label("Time")
+            .y_axis_label("Latency")
+            .show(ui, |plot_ui| {
+                puffin::profile_scope!("Plot_draw");
+
+                if let Ok(asdf) = self.timeseries_hash.lock() {
+                    for (target, series) in asdf.iter() {
+                        self.draw_target(plot_ui, target, series, f_elapsed, y_range);
+                    }
+                }
+
+                self.draw_annotations(plot_ui, None, y_range);
+
+                if self.follow_live || jump_to_range {
+                    plot_ui.set_plot_bounds(self.time_plot_bounds(visible_range, y_range));
+                }
+
+                plot_ui.plot_bounds()
+            });
+
+        self.handle_time_plot_response(response.response.double_clicked(), response.inner);
+    }
+
+    fn show_small_multiples(
+        &mut self,
+        ui: &mut egui::Ui,
+        f_elapsed: f64,
+        visible_range: (f64, f64),
+        y_range: (f64, f64),
+        jump_to_range: bool,
+    ) {
+        let timeseries_hash = self.timeseries_hash.clone();
+        let Ok(ts_hash) = timeseries_hash.lock() else {
+            return;
+        };
+
+        let mut targets: Vec<_> = ts_hash
+            .iter()
+            .filter(|(target, _)| self.target_styles.get(*target).is_some_and(|x| x.visible))
+            .collect();
+        targets.sort_by(|a, b| a.0.cmp(b.0));
+
+        if targets.is_empty() {
+            return;
+        }
+
+        let row_height = (ui.available_height() / targets.len() as f32
+            - ui.spacing().item_spacing.y)
+            .max(MIN_SUBPLOT_HEIGHT);
+
+        let mut double_clicked = false;
+        let mut bounds = None;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (idx, (target, series)) in targets.iter().enumerate() {
+                let y_range = if self.shared_y {
+                    y_range
+                } else {
+                    let values: Vec<_> = series.values_younger_than(visible_range.0).collect();
+                    self.yaxis.range(&values, self.top_padding)
+                };
+
+                let response = self
+                    .time_plot(self.id_source.with(("plot", target.as_str())))
+                    .height(row_height)
+                    .link_axis(self.time_link_group(), true, self.shared_y)
+                    // Only the bottom subplot carries the shared time axis
+                    .show_axes([idx + 1 == targets.len(), true])
+                    .y_axis_label(target.as_str())
+                    .show(ui, |plot_ui| {
+                        puffin::profile_scope!("Plot_draw");
+
+                        self.draw_target(plot_ui, target, series, f_elapsed, y_range);
+                        self.draw_annotations(plot_ui, Some(target), y_range);
+
+                        if self.follow_live || jump_to_range {
+                            plot_ui.set_plot_bounds(self.time_plot_bounds(visible_range, y_range));
+                        }
+
+                        plot_ui.plot_bounds()
+                    });
+
+                double_clicked |= response.response.double_clicked();
+                bounds.get_or_insert(response.inner);
+            }
+        });
+
+        drop(ts_hash);
+
+        if let Some(bounds) = bounds {
+            self.handle_time_plot_response(double_clicked, bounds);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use egui_ping_graph::{
+    clock::SharedClock,
+    probe::ProbeRuntime,
+    sample_json,
+    series::{SampleCursor, Series},
+    stats_json,
+};
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use color_eyre::eyre::{Report, WrapErr};
+
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+async fn write_output(
+    out: &mut impl Write,
+    timeseries_hashref: Arc<Mutex<HashMap<String, Series>>>,
+    clock: SharedClock,
+    stats_interval: Option<f64>,
+) -> Result<(), Report> {
+    let get_sec_elapsed = || clock.elapsed();
+
+    let mut cursor = SampleCursor::default();
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut next_stats = stats_interval.map(|x| get_sec_elapsed() + x);
+
+    loop {
+        interval.tick().await;
+
+        let mut lines = vec![];
+
+        if let Ok(ts_hash) = timeseries_hashref.lock() {
+            let mut targets: Vec<_> = ts_hash.iter().collect();
+            targets.sort_by(|a, b| a.0.cmp(b.0));
+
+            for (target, series) in &targets {
+                for sample in cursor.read_new(target, series) {
+                    lines.push(sample_json(clock.as_ref(), target, &sample));
+                }
+            }
+
+            let now = get_sec_elapsed();
+            if let (Some(next), Some(every)) = (next_stats.as_mut(), stats_interval) {
+                if now >= *next {
+                    *next = now + every;
+
+                    for (target, series) in &targets {
+                        lines.push(stats_json(clock.as_ref(), now, target, series));
+                    }
+                }
+            }
+        }
+
+        for line in lines {
+            writeln!(out, "{line}")?;
+        }
+        out.flush()?;
+    }
+}
+
+// Runs the probes without a window, printing one JSON object per line until interrupted
+pub fn run(
+    probes: &ProbeRuntime,
+    targets: &[String],
+    output: Option<&Path>,
+    stats_interval: Option<f64>,
+) -> Result<(), Report> {
+    let mut out: Box<dyn Write> = match output {
+        Some(path) => Box::new(std::io::LineWriter::new(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .wrap_err_with(|| format!("Failed to open {}", path.display()))?,
+        )),
+        None => Box::new(std::io::stdout().lock()),
+    };
+
+    for target in targets {
+        probes.add_target(target);
+    }
+
+    probes.block_on(async {
+        tokio::select! {
+            result = write_output(&mut out, probes.timeseries.clone(), probes.clock(), stats_interval) => result,
+            _ = tokio::signal::ctrl_c() => Ok(()),
+        }
+    })
+}
@@ -1,12 +1,147 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use color_eyre::Report;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Args {
     /// Verbosity log
-    #[arg(short, long, action = clap::ArgAction::Count)]
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
     pub verbose: u8,
+
+    /// Path to a TOML config file
+    #[arg(short, long)]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Serve Prometheus metrics on this address, e.g. 127.0.0.1:9898
+    #[arg(long)]
+    pub metrics_listen: Option<std::net::SocketAddr>,
+
+    /// Show the targets of the agent at this address, can be repeated
+    #[arg(long = "agent")]
+    pub agents: Vec<String>,
+
+    /// Serve the JSON control API on this Unix socket
+    #[arg(long)]
+    pub control_socket: Option<std::path::PathBuf>,
+
+    /// Serve the JSON control API on this loopback address, e.g. 127.0.0.1:9899
+    #[arg(long)]
+    pub control_listen: Option<std::net::SocketAddr>,
+
+    /// Host to ping, can be repeated. Replaces the saved target list
+    #[arg(short, long = "target", value_parser = egui_ping_graph::probe::parse_target)]
+    pub targets: Vec<String>,
+
+    /// Run without a window, printing one JSON object per sample
+    #[arg(long, conflicts_with = "tui")]
+    pub headless: bool,
+
+    /// Draw the graphs in the terminal instead of a window
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Write log messages to this file instead of stderr. Discarded in --tui mode when unset
+    #[arg(long, global = true)]
+    pub log_file: Option<std::path::PathBuf>,
+
+    /// Write headless output to this file instead of stdout
+    #[arg(short, long, requires = "headless")]
+    pub output: Option<std::path::PathBuf>,
+
+    /// Also print per-target statistics every this many seconds in headless mode
+    #[arg(long, requires = "headless")]
+    pub stats_interval: Option<f64>,
+
+    /// Upper limit on UI redraws per second
+    #[arg(long, default_value_t = 30)]
+    pub max_fps: u32,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    pub fn is_gui(&self) -> bool {
+        self.command.is_none() && !self.headless && !self.tui
+    }
+
+    // Given before or after the subcommand, `check` only takes the long form since -c is
+    // its critical threshold
+    pub fn config_path(&self) -> Option<&std::path::Path> {
+        let command_config = match &self.command {
+            Some(Command::Run(args)) => args.config.as_deref(),
+            Some(Command::Check(args)) => args.config.as_deref(),
+            Some(Command::Agent(args)) => args.config.as_deref(),
+            None => None,
+        };
+
+        command_config.or(self.config.as_deref())
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Ping the targets for a while, print a summary and exit
+    Run(RunArgs),
+    /// Nagios/Icinga compatible check of a single target
+    Check(CheckArgs),
+    /// Ping the targets without a window and stream the samples to connecting viewers
+    Agent(AgentArgs),
+}
+
+#[derive(clap::Args)]
+pub struct RunArgs {
+    /// Hosts to ping
+    #[arg(required = true, value_parser = egui_ping_graph::probe::parse_target)]
+    pub targets: Vec<String>,
+
+    /// Replies or timeouts to collect per target, defaults to 10 without --duration
+    #[arg(short = 'n', long)]
+    pub count: Option<usize>,
+
+    /// Stop after this long, e.g. 30s or 2m
+    #[arg(short, long, value_parser = egui_ping_graph::parse_duration)]
+    pub duration: Option<f64>,
+
+    /// Fail when a target loses more than this percentage of pings
+    #[arg(long)]
+    pub max_loss: Option<f64>,
+
+    /// Fail when the average round trip time of a target is above this, e.g. 50ms
+    #[arg(long, value_parser = egui_ping_graph::parse_duration)]
+    pub max_avg: Option<f64>,
+
+    /// Fail when the 95th percentile round trip time of a target is above this
+    #[arg(long, value_parser = egui_ping_graph::parse_duration)]
+    pub max_p95: Option<f64>,
+
+    /// Fail when the jitter of a target is above this
+    #[arg(long, value_parser = egui_ping_graph::parse_duration)]
+    pub max_jitter: Option<f64>,
+
+    /// Path to a TOML config file
+    #[arg(short, long)]
+    pub config: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args)]
+pub struct AgentArgs {
+    /// Hosts to ping
+    #[arg(required = true, value_parser = egui_ping_graph::probe::parse_target)]
+    pub targets: Vec<String>,
+
+    /// Address to serve samples on. Viewers aren't authenticated, pass e.g. 0.0.0.0:9900 to
+    /// serve other hosts
+    #[arg(short, long, default_value = "127.0.0.1:9900")]
+    pub listen: std::net::SocketAddr,
+
+    /// Name viewers prefix the targets with, defaults to the host name
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Path to a TOML config file
+    #[arg(short, long)]
+    pub config: Option<std::path::PathBuf>,
 }
 
 const VERBOSE_LEVEL: &[&str] = &["info", "debug", "trace"];
@@ -20,7 +155,7 @@ macro_rules! get_this_pkg_name {
 pub fn initialize() -> Result<Args, Report> {
     use tracing_error::ErrorLayer;
     use tracing_subscriber::prelude::*;
-    use tracing_subscriber::{fmt, EnvFilter};
+    use tracing_subscriber::{fmt, fmt::writer::BoxMakeWriter, EnvFilter};
 
     color_eyre::install()?;
     let args = Args::parse();
@@ -41,7 +176,22 @@ pub fn initialize() -> Result<Args, Report> {
         None => env_filter,
     };
 
-    let fmt_layer = fmt::layer().with_writer(std::io::stderr);
+    // The terminal UI owns the tty, anything printed to stderr would end up drawn over it
+    let writer = match &args.log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            BoxMakeWriter::new(std::sync::Mutex::new(file))
+        }
+        None if args.tui && args.command.is_none() => BoxMakeWriter::new(std::io::sink),
+        None => BoxMakeWriter::new(std::io::stderr),
+    };
+
+    let fmt_layer = fmt::layer()
+        .with_ansi(args.log_file.is_none())
+        .with_writer(writer);
 
     let fmt_layer = match verbosity {
         Some(_) => {
@@ -65,3 +215,25 @@ pub fn initialize() -> Result<Args, Report> {
 
     Ok(args)
 }
+
+#[derive(clap::Args)]
+pub struct CheckArgs {
+    /// Host to ping
+    pub target: String,
+
+    /// Replies or timeouts to collect
+    #[arg(short = 'n', long, default_value_t = 5)]
+    pub count: usize,
+
+    /// Warning threshold as <rta ms>,<loss>%[,<jitter ms>]
+    #[arg(short, long, default_value = "100,20%")]
+    pub warning: String,
+
+    /// Critical threshold as <rta ms>,<loss>%[,<jitter ms>]
+    #[arg(short, long, default_value = "500,60%")]
+    pub critical: String,
+
+    /// Path to a TOML config file
+    #[arg(long)]
+    pub config: Option<std::path::PathBuf>,
+}
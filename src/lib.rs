@@ -0,0 +1,52 @@
+//! Live ping latency graphs for egui.
+//!
+//! Start a [`ProbeRuntime`], add targets to it and draw them with a [`PingGraph`] from any
+//! egui app. The `egui-ping-graph` binary is one such app.
+
+// Public items must not expose types an embedding app can't name
+#![warn(unnameable_types)]
+
+mod agent;
+mod alert;
+mod anomaly;
+pub mod app;
+mod app_agent;
+mod app_alerter;
+mod app_cleaner;
+mod app_control;
+mod app_exporter;
+mod app_metrics;
+mod app_pinger;
+pub mod clock;
+pub mod config;
+mod control;
+mod correlation;
+pub mod events;
+mod export;
+mod funcs;
+pub mod graph;
+mod metrics;
+mod notify;
+pub mod probe;
+pub mod series;
+mod smoothing;
+pub mod style;
+mod view_alerts;
+mod view_correlation;
+mod view_events;
+mod view_heatmap;
+mod view_histogram;
+mod yaxis;
+
+pub use funcs::{
+    fmt_float_s, parse_duration, pingstat_from_rawdata, sample_json, stats_json, x_axis_fmt,
+    y_axis_fmt,
+};
+pub use graph::PingGraph;
+pub use probe::ProbeRuntime;
+
+// const DEFAULT_HISTORY_SECS: f64 = 10.0;
+pub const DEFAULT_OFFSET: f64 = 1.0;
+pub const MAX_HISTORY_SECS: f64 = 120.0;
+
+pub const DEFAULT_TARGETS: &[&str] = &["8.8.8.8", "9.9.9.9", "1.1.1.1"];
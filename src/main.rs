@@ -1,113 +1,39 @@
 // This is to disable console window
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use color_eyre::eyre::Report;
-use rand::Rng;
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    vec,
-};
-
-use crate::series::Series;
-
-mod app_cleaner;
-mod app_pinger;
-mod funcs;
+mod agent_mode;
+mod check;
+mod headless;
 mod init;
-mod series;
-mod series_new;
-mod statics;
-
-// const DEFAULT_HISTORY_SECS: f64 = 10.0;
-const DEFAULT_OFFSET: f64 = 1.0;
-const MAX_HISTORY_SECS: f64 = 120.0;
-
-const MAX_FPS: u32 = 30;
-const MAX_FRAME_SLEEP: f64 = 1000.0 / MAX_FPS as f64;
-const MAX_FRAME_SLEEP_DUR: std::time::Duration =
-    std::time::Duration::from_millis(MAX_FRAME_SLEEP as u64);
-
-pub struct App {
-    // t_since_start: time::Instant,
-    // datetime_since_start: time::OffsetDateTime,
-    timeseries_hash: Arc<Mutex<HashMap<String, Series>>>,
-    color_preset: Vec<egui::Color32>,
-    color_preset_shfidx: Vec<u8>,
-
-    history_window: f64,
-
-    top_padding: f64,
-}
+mod oneshot;
+mod tui;
 
-impl Default for App {
-    fn default() -> Self {
-        use rand::seq::SliceRandom;
-
-        let color_preset: Vec<_> = ["#48B7B2", "#7A48B7", "#B7484D", "#85B748"]
-            .iter()
-            .map(|&x| egui::Color32::from_hex(x).unwrap())
-            .collect();
-
-        let mut rng = rand::thread_rng();
-        let mut color_preset_shfidx: Vec<_> = (0u8..color_preset.len() as u8).collect();
-        color_preset_shfidx.shuffle(&mut rng);
-
-        Self {
-            color_preset,
-            color_preset_shfidx,
-
-            timeseries_hash: Arc::new(Mutex::new(HashMap::new())),
-            // t_since_start: time::Instant::now(),
-            // datetime_since_start: time::OffsetDateTime::now_utc(),
-            history_window: 10.0,
-
-            top_padding: 0.01,
-        }
-    }
-}
+use color_eyre::eyre::Report;
+use egui_ping_graph::{app, config, probe, DEFAULT_TARGETS};
 
-#[allow(dead_code)]
-impl App {
-    fn get_sec_since_start(&self) -> f64 {
-        (time::Instant::now() - *statics::I_START.get().unwrap()).as_seconds_f64()
+#[tracing::instrument]
+fn main() -> Result<(), Report> {
+    let args = init::initialize()?;
+    let mut config = config::Config::load(args.config_path())?;
+    if args.metrics_listen.is_some() {
+        config.metrics_listen = args.metrics_listen;
     }
-
-    fn get_highest_value(&self) -> f64 {
-        if let Ok(values) = self.timeseries_hash.clone().lock() {
-            values
-                .iter()
-                .map(|x| x.1)
-                .map(|x| x.get_highest_value())
-                .max_by(|a, b| a.total_cmp(b))
-                .unwrap_or(0.0)
-        } else {
-            0.0
-        }
+    config
+        .agents
+        .extend(args.agents.iter().map(|addr| config::AgentConfig {
+            addr: addr.clone(),
+            name: None,
+        }));
+    let has_agents = !config.agents.is_empty();
+    if args.control_socket.is_some() {
+        config.control_socket = args.control_socket.clone();
     }
-
-    fn get_highest_value_youngerthan(&self, time: f64) -> f64 {
-        if let Ok(values) = self.timeseries_hash.clone().lock() {
-            values
-                .iter()
-                .map(|x| x.1)
-                .map(|x| x.get_highest_value_youngerthan(time))
-                .max_by(|a, b| a.total_cmp(b))
-                .unwrap_or(0.0)
-        } else {
-            0.0
-        }
+    if args.control_listen.is_some() {
+        config.control_listen = args.control_listen;
     }
-}
-
-#[tracing::instrument]
-fn main() -> Result<(), Report> {
-    init::initialize()?;
 
     #[cfg(debug_assertions)]
-    {
-        println!("issa debug'a");
-
+    if args.is_gui() {
         puffin::set_scopes_on(true); // tell puffin to collect data
 
         match puffin_http::Server::new("127.0.0.1:8585") {
@@ -133,187 +59,57 @@ fn main() -> Result<(), Report> {
         };
     }
 
-    {
-        // OnceCell statics init
-
-        statics::DATETIME_START.get_or_init(time::OffsetDateTime::now_utc);
-        statics::I_START.get_or_init(time::Instant::now);
-    }
-
-    let options = eframe::NativeOptions::default();
-    let state = Box::<App>::default();
-
-    let targets: Vec<_> = vec!["8.8.8.8", "9.9.9.9", "1.1.1.1"]
-        .into_iter()
-        .map(String::from)
-        .collect();
-
-    {
-        use futures::future::select_all;
-        use tokio::runtime::Runtime;
-
-        let rt = Runtime::new()?;
-        let _enter = rt.enter();
-        let timeseries_hashclone = state.timeseries_hash.clone();
-        let available_idxcolor = state.color_preset_shfidx.clone();
-        let color_preset_len = state.color_preset.len() as u8;
-
-        std::thread::spawn(move || {
-            rt.block_on(async {
-                let curr_time = time::Instant::now();
-                let mut available_idxcolor = available_idxcolor;
-
-                let pinger_handles: Vec<_> = targets
-                    .clone()
-                    .into_iter()
-                    .map(|target| {
-                        let timeseries_hashref = timeseries_hashclone.clone();
-                        let idxcolor = available_idxcolor
-                            .pop()
-                            .unwrap_or_else(|| rand::thread_rng().gen_range(0..color_preset_len));
-
-                        tokio::spawn(async move {
-                            app_pinger::run_pinger(
-                                target.clone(),
-                                curr_time,
-                                timeseries_hashref,
-                                idxcolor,
-                            )
-                        })
-                    })
-                    .collect();
-
-                let cleaner_handles: Vec<_> = targets
-                    .clone()
-                    .into_iter()
-                    .map(|target| {
-                        let timeseries_hashref = timeseries_hashclone.clone();
-
-                        tokio::spawn(async move {
-                            app_cleaner::run_cleaner(target.clone(), curr_time, timeseries_hashref)
-                                .await
-                        })
-                    })
-                    .collect();
+    let probes = probe::ProbeRuntime::start(config)?;
 
-                tokio::select! {
-                    _ = select_all(pinger_handles) => {}
-                    _ = select_all(cleaner_handles) => {}
-                }
-            });
-        });
+    match &args.command {
+        Some(init::Command::Run(run_args)) => {
+            if !oneshot::run(&probes, run_args) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some(init::Command::Check(check_args)) => {
+            let status = check::run(&probes, check_args);
+            std::process::exit(status.exit_code());
+        }
+        Some(init::Command::Agent(agent_args)) => {
+            return agent_mode::run(&probes, agent_args);
+        }
+        None => {}
     }
 
-    eframe::run_native("app_name", options, Box::new(|_ctx| state)).unwrap();
+    if args.headless || args.tui {
+        // Agents alone are enough to have something to show
+        let targets = if args.targets.is_empty() && !has_agents {
+            DEFAULT_TARGETS.iter().map(|&x| x.to_string()).collect()
+        } else {
+            args.targets
+        };
 
-    Ok(())
-}
+        if args.tui {
+            return tui::run(&probes, &targets);
+        }
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        puffin::profile_scope!("update");
-        let frame_istart = std::time::Instant::now();
-        let f_elapsed = self.get_sec_since_start() / 1.0;
-        let start_time = *statics::I_START.get().unwrap();
-        let highest_value = self.get_highest_value_youngerthan(
-            (frame_istart - start_time).as_seconds_f64() - self.history_window,
+        return headless::run(
+            &probes,
+            &targets,
+            args.output.as_deref(),
+            args.stats_interval,
         );
+    }
 
-        ctx.set_visuals(egui::Visuals::dark());
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            puffin::profile_scope!("CentralPanel_draw");
-
-            egui::CollapsingHeader::new("Options")
-                .default_open(false)
-                .show_unindented(ui, |ui| {
-                    ui.horizontal_wrapped(|ui| {
-                        ui.label("Window size");
-                        ui.add(egui::Slider::new(
-                            &mut self.history_window,
-                            5.0..=MAX_HISTORY_SECS,
-                        ));
-                    });
-
-                    egui::CollapsingHeader::new("Extra Options")
-                        .default_open(false)
-                        .show(ui, |ui| {
-                            ui.horizontal(|ui| {
-                                ui.label("Top Padding");
-                                ui.add(egui::Slider::new(&mut self.top_padding, 0.05..=2.0));
-                            })
-                        });
-                });
-
-            if let Ok(asdf) = self.timeseries_hash.lock() {
-                egui::CollapsingHeader::new("Details")
-                    .default_open(true)
-                    .show_unindented(ui, |ui| {
-                        egui::Grid::new("pingmeasurements").show(ui, |ui| {
-                            for (target, series) in asdf.iter() {
-                                let stats = &series.stats;
-
-                                ui.label(target);
-
-                                ui.label(format!("last: {}", funcs::fmt_float_s(stats.last)));
-                                ui.label(format!("min: {}", funcs::fmt_float_s(stats.min)));
-                                ui.label(format!("max: {}", funcs::fmt_float_s(stats.max)));
-                                ui.label(format!("avg: {}", funcs::fmt_float_s(stats.avg)));
-                                ui.label(format!("jtr: {}", funcs::fmt_float_s(stats.jitter)));
-                                ui.label(format!("p95: {}", funcs::fmt_float_s(stats.p95)));
-                                ui.label(format!("t/0: {}", stats.timeouts));
-                                ui.end_row();
-                            }
-                        })
-                    });
-            }
-
-            let plot = egui_plot::Plot::new("mesurment");
-            plot.allow_boxed_zoom(false)
-                .allow_double_click_reset(false)
-                .allow_drag(false)
-                .allow_scroll(false)
-                .allow_zoom(false)
-                .legend(egui_plot::Legend::default())
-                .x_axis_formatter(|val, _, _| funcs::x_axis_fmt(val))
-                .y_axis_formatter(|val, _, _| funcs::y_axis_fmt(val))
-                .x_axis_label("Time")
-                .y_axis_label("Latency")
-                .label_formatter(funcs::xy_label_fmt)
-                .show(ui, |plot_ui| {
-                    puffin::profile_scope!("Plot_draw");
-
-                    if let Ok(asdf) = self.timeseries_hash.lock() {
-                        for (target, series) in asdf.iter() {
-                            puffin::profile_scope!("series_iter", target);
-                            for points in series.splitted_to_plotpoints() {
-                                puffin::profile_scope!("lines_iter");
-                                plot_ui.line(
-                                    egui_plot::Line::new(points)
-                                        .color(self.color_preset[series.linecol_idx as usize])
-                                        .width(3.0)
-                                        .fill(0.0)
-                                        .name(target),
-                                )
-                            }
-                        }
-                    }
-
-                    plot_ui.set_plot_bounds(egui_plot::PlotBounds::from_min_max(
-                        [f_elapsed - DEFAULT_OFFSET - self.history_window, 0.0],
-                        [f_elapsed - DEFAULT_OFFSET, highest_value + self.top_padding],
-                    ));
-                })
-        });
-
-        {
-            puffin::profile_scope!("fpslimit_sleep");
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size(app::DEFAULT_WINDOW_SIZE),
+        ..Default::default()
+    };
+    let frame_interval = std::time::Duration::from_secs_f64(1.0 / args.max_fps.max(1) as f64);
 
-            let threadsleep_dur = MAX_FRAME_SLEEP_DUR - frame_istart.elapsed();
-            std::thread::sleep(threadsleep_dur);
-        }
+    eframe::run_native(
+        env!("CARGO_PKG_NAME"),
+        options,
+        Box::new(move |cc| Box::new(app::App::new(cc, probes, args.targets, frame_interval))),
+    )
+    .unwrap();
 
-        ctx.request_repaint();
-        puffin::GlobalProfiler::lock().new_frame();
-    }
+    Ok(())
 }
@@ -0,0 +1,326 @@
+use crate::{
+    events::{EventKind, EventLog},
+    series::{PingStatistics, SampleCursor, Series},
+};
+
+use std::{collections::HashMap, fmt::Write};
+
+type MetricValue = fn(&TargetMetrics) -> Option<f64>;
+
+// Upper bounds of the RTT histogram buckets, in seconds
+const RTT_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+];
+
+#[derive(Default)]
+pub struct TargetMetrics {
+    // Cumulative, bucket_counts[i] counts samples <= RTT_BUCKETS[i]
+    bucket_counts: Vec<u64>,
+    rtt_sum: f64,
+    rtt_count: u64,
+    timeouts: u64,
+    probe_errors: u64,
+    last_rtt: Option<f64>,
+    stats: PingStatistics,
+    last_active: f64,
+}
+
+impl TargetMetrics {
+    fn record_rtt(&mut self, rtt: f64) {
+        if self.bucket_counts.is_empty() {
+            self.bucket_counts = vec![0; RTT_BUCKETS.len()];
+        }
+
+        for (count, _) in self
+            .bucket_counts
+            .iter_mut()
+            .zip(RTT_BUCKETS)
+            .filter(|(_, &le)| rtt <= le)
+        {
+            *count += 1;
+        }
+
+        self.rtt_sum += rtt;
+        self.rtt_count += 1;
+        self.last_rtt = Some(rtt);
+    }
+
+    fn ingest(&mut self, target: &str, series: &Series, cursor: &mut SampleCursor) {
+        for sample in cursor.read_new(target, series) {
+            match sample.rtt {
+                Some(rtt) => self.record_rtt(rtt),
+                None => self.timeouts += 1,
+            }
+            self.last_active = sample.time;
+        }
+
+        self.stats = series.stats.clone();
+    }
+}
+
+// Cumulative per-target counters fed from the shared series, the series themselves only
+// keep a sliding window so new samples get folded in here before they are cleaned up
+#[derive(Default)]
+pub struct Metrics {
+    targets: HashMap<String, TargetMetrics>,
+    cursor: SampleCursor,
+    // Events pushed to the log so far
+    seen_events: u64,
+}
+
+fn escape_label(val: &str) -> String {
+    val.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Metrics {
+    pub fn ingest(&mut self, timeseries: &HashMap<String, Series>, events: &EventLog, now: f64) {
+        for (target, series) in timeseries {
+            self.targets.entry(target.clone()).or_default().ingest(
+                target,
+                series,
+                &mut self.cursor,
+            );
+        }
+
+        for event in events.pushed_after(self.seen_events) {
+            if let EventKind::ProbeError(_) = event.kind {
+                let metrics = self.targets.entry(event.target.clone()).or_default();
+                metrics.probe_errors += 1;
+                metrics.last_active = metrics.last_active.max(event.time);
+            }
+        }
+        self.seen_events = events.pushed();
+
+        // Forget removed targets once they have been quiet for a while
+        self.targets.retain(|target, metrics| {
+            timeseries.contains_key(target) || metrics.last_active > now - crate::MAX_HISTORY_SECS
+        });
+        self.cursor.retain(|target| timeseries.contains_key(target));
+    }
+
+    pub fn render(&self) -> String {
+        let mut targets: Vec<_> = self.targets.iter().collect();
+        targets.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut out = String::new();
+
+        let _ = writeln!(
+            out,
+            "# HELP ping_rtt_seconds Round trip time of successful pings."
+        );
+        let _ = writeln!(out, "# TYPE ping_rtt_seconds histogram");
+        for (target, metrics) in &targets {
+            let target = escape_label(target);
+
+            for (idx, le) in RTT_BUCKETS.iter().enumerate() {
+                let count = metrics.bucket_counts.get(idx).copied().unwrap_or(0);
+                let _ = writeln!(
+                    out,
+                    "ping_rtt_seconds_bucket{{target=\"{target}\",le=\"{le}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "ping_rtt_seconds_bucket{{target=\"{target}\",le=\"+Inf\"}} {}",
+                metrics.rtt_count
+            );
+            let _ = writeln!(
+                out,
+                "ping_rtt_seconds_sum{{target=\"{target}\"}} {}",
+                metrics.rtt_sum
+            );
+            let _ = writeln!(
+                out,
+                "ping_rtt_seconds_count{{target=\"{target}\"}} {}",
+                metrics.rtt_count
+            );
+        }
+
+        let families: [(&str, &str, &str, MetricValue); 9] = [
+            (
+                "ping_timeouts_total",
+                "counter",
+                "Pings that timed out.",
+                |x| Some(x.timeouts as f64),
+            ),
+            (
+                "ping_probe_errors_total",
+                "counter",
+                "Times the ping process failed and was restarted.",
+                |x| Some(x.probe_errors as f64),
+            ),
+            (
+                "ping_last_rtt_seconds",
+                "gauge",
+                "Most recent round trip time.",
+                |x| x.last_rtt,
+            ),
+            (
+                "ping_window_min_seconds",
+                "gauge",
+                "Lowest round trip time in the history window.",
+                |x| Some(x.stats.min),
+            ),
+            (
+                "ping_window_max_seconds",
+                "gauge",
+                "Highest round trip time in the history window.",
+                |x| Some(x.stats.max),
+            ),
+            (
+                "ping_window_avg_seconds",
+                "gauge",
+                "Mean round trip time in the history window.",
+                |x| Some(x.stats.avg),
+            ),
+            (
+                "ping_window_jitter_seconds",
+                "gauge",
+                "Standard deviation of the round trip time in the history window.",
+                |x| Some(x.stats.jitter),
+            ),
+            (
+                "ping_window_p95_seconds",
+                "gauge",
+                "95th percentile round trip time in the history window.",
+                |x| Some(x.stats.p95),
+            ),
+            (
+                "ping_window_timeouts",
+                "gauge",
+                "Timeouts in the history window.",
+                |x| Some(x.stats.timeouts as f64),
+            ),
+        ];
+
+        for (name, kind, help, value) in families {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (target, metrics) in &targets {
+                // Statistics of an empty window come out as NaN or infinite
+                let Some(value) = value(metrics).filter(|x| x.is_finite()) else {
+                    continue;
+                };
+
+                let _ = writeln!(out, "{name}{{target=\"{}\"}} {value}", escape_label(target));
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    use std::sync::Arc;
+
+    fn event_log() -> EventLog {
+        let clock =
+            ManualClock::new(time::OffsetDateTime::from_unix_timestamp(1_704_067_200).unwrap());
+        EventLog::new(Arc::new(clock))
+    }
+
+    fn series(samples: &[Option<f64>]) -> Series {
+        let mut series = Series::default();
+
+        for (idx, rtt) in samples.iter().enumerate() {
+            match rtt {
+                Some(rtt) => series.push(Some((idx as f64, *rtt))),
+                None => {
+                    series.timeout_at.push(idx as f64);
+                    series.push(None)
+                }
+            };
+        }
+        series.update_pingstat();
+
+        series
+    }
+
+    fn lines<'a>(out: &'a str, prefix: &str) -> Vec<&'a str> {
+        out.lines().filter(|x| x.starts_with(prefix)).collect()
+    }
+
+    #[test]
+    fn counts_probe_errors_sharing_a_time() {
+        let mut metrics = Metrics::default();
+        let mut events = event_log();
+        let ts = HashMap::from([("host".to_string(), series(&[Some(0.01)]))]);
+
+        events.push(1.0, "host", EventKind::ProbeError("a".into()));
+        metrics.ingest(&ts, &events, 1.0);
+        events.push(1.0, "host", EventKind::ProbeError("b".into()));
+        events.push(1.0, "host", EventKind::ProbeError("c".into()));
+        metrics.ingest(&ts, &events, 1.0);
+        metrics.ingest(&ts, &events, 2.0);
+
+        assert_eq!(metrics.targets["host"].probe_errors, 3);
+    }
+
+    #[test]
+    fn histogram_is_cumulative() {
+        let mut metrics = Metrics::default();
+        let ts = HashMap::from([(
+            "host".to_string(),
+            series(&[Some(0.003), Some(0.02), None, Some(0.2)]),
+        )]);
+        metrics.ingest(&ts, &event_log(), 4.0);
+
+        let out = metrics.render();
+        let buckets: Vec<_> = lines(&out, "ping_rtt_seconds_bucket")
+            .iter()
+            .map(|x| x.rsplit(' ').next().unwrap())
+            .collect();
+
+        assert_eq!(
+            buckets,
+            ["0", "0", "1", "1", "2", "2", "2", "3", "3", "3", "3", "3"]
+        );
+        assert_eq!(
+            lines(&out, "ping_rtt_seconds_bucket{target=\"host\",le=\"+Inf\"}"),
+            ["ping_rtt_seconds_bucket{target=\"host\",le=\"+Inf\"} 3"]
+        );
+        assert_eq!(
+            lines(&out, "ping_rtt_seconds_count"),
+            ["ping_rtt_seconds_count{target=\"host\"} 3"]
+        );
+        assert_eq!(
+            lines(&out, "ping_timeouts_total"),
+            ["ping_timeouts_total{target=\"host\"} 1"]
+        );
+    }
+
+    #[test]
+    fn escapes_labels() {
+        let mut metrics = Metrics::default();
+        let ts = HashMap::from([("a\"b\\c\nd".to_string(), series(&[Some(0.01)]))]);
+        metrics.ingest(&ts, &event_log(), 1.0);
+
+        assert_eq!(
+            lines(&metrics.render(), "ping_last_rtt_seconds{"),
+            ["ping_last_rtt_seconds{target=\"a\\\"b\\\\c\\nd\"} 0.01"]
+        );
+    }
+
+    #[test]
+    fn skips_values_of_empty_windows() {
+        let mut metrics = Metrics::default();
+        let ts = HashMap::from([("host".to_string(), series(&[None, None]))]);
+        metrics.ingest(&ts, &event_log(), 2.0);
+
+        let out = metrics.render();
+
+        assert!(!out.contains("NaN") && !out.contains("inf"));
+        assert!(lines(&out, "ping_last_rtt_seconds{").is_empty());
+        assert!(lines(&out, "ping_window_min_seconds{").is_empty());
+        assert_eq!(
+            lines(&out, "ping_window_timeouts{"),
+            ["ping_window_timeouts{target=\"host\"} 2"]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    alert::{AlertEvent, AlertTransition},
+    clock::SharedClock,
+    funcs,
+};
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use color_eyre::eyre::{bail, eyre, Report, WrapErr};
+use serde::Deserialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+const WEBHOOK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum SinkKind {
+    // Runs through the platform shell with the alert details in ALERT_* env vars
+    Command { command: String },
+    // Plain http:// only, checked when the config is loaded
+    Webhook { url: String },
+    File { path: PathBuf },
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SinkConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    // Minimum time between two firing notifications for the same rule and target
+    #[serde(default)]
+    pub rate_limit_secs: f64,
+}
+
+impl SinkConfig {
+    pub fn validate(&self) -> Result<(), Report> {
+        if let SinkKind::Webhook { url } = &self.kind {
+            split_http_url(url)
+                .wrap_err_with(|| format!("Invalid webhook for sink {}", self.name))?;
+        }
+
+        Ok(())
+    }
+}
+
+// `time` is the event time already formatted, sinks run detached from the clock
+fn event_env(event: &AlertEvent, time: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("ALERT_RULE", event.rule.name.clone()),
+        ("ALERT_TARGET", event.target.clone()),
+        ("ALERT_STATE", event.transition.label().to_string()),
+        ("ALERT_METRIC", event.rule.metric.label().to_string()),
+        (
+            "ALERT_VALUE",
+            event.value.map(|x| x.to_string()).unwrap_or_default(),
+        ),
+        ("ALERT_THRESHOLD", event.rule.threshold.to_string()),
+        ("ALERT_TIME", time.to_string()),
+        ("ALERT_DESCRIPTION", event.describe()),
+    ]
+}
+
+fn event_json(event: &AlertEvent, time: &str) -> serde_json::Value {
+    serde_json::json!({
+        "rule": event.rule.name,
+        "target": event.target,
+        "state": event.transition.label(),
+        "metric": event.rule.metric.label(),
+        "value": event.value,
+        "threshold": event.rule.threshold,
+        "time": time,
+        "description": event.describe(),
+    })
+}
+
+async fn run_command(command: &str, event: &AlertEvent, time: &str) -> Result<(), Report> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+
+    let status = cmd
+        .arg(command)
+        .envs(event_env(event, time))
+        .stdin(std::process::Stdio::null())
+        .status()
+        .await
+        .wrap_err_with(|| format!("Failed to run `{command}`"))?;
+
+    if !status.success() {
+        bail!("`{command}` exited with {status}");
+    }
+
+    Ok(())
+}
+
+pub fn split_http_url(url: &str) -> Result<(String, String), Report> {
+    if url.starts_with("https://") {
+        bail!("https:// is not supported, use an http:// endpoint or a command sink, got {url}");
+    }
+
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| eyre!("Only http:// URLs are supported, got {url}"))?;
+
+    let (authority, path) = match rest.find('/') {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, "/"),
+    };
+
+    // An IPv6 literal is bracketed and has colons of its own
+    let has_port = match authority.rfind(']') {
+        Some(idx) => authority[idx..].contains(':'),
+        None => authority.contains(':'),
+    };
+
+    if authority.is_empty() {
+        bail!("No host in {url}");
+    }
+
+    let authority = if has_port {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+
+    Ok((authority, path.to_string()))
+}
+
+pub async fn http_post(url: &str, content_type: &str, body: &[u8]) -> Result<(), Report> {
+    let (authority, path) = split_http_url(url)?;
+
+    let request = async {
+        let mut stream = tokio::net::TcpStream::connect(&authority).await?;
+
+        let head = format!(
+            "POST {path} HTTP/1.1\r\nHost: {authority}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body).await?;
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response).await?;
+
+        Ok::<_, std::io::Error>(response)
+    };
+
+    let response = tokio::time::timeout(WEBHOOK_TIMEOUT, request)
+        .await
+        .wrap_err_with(|| format!("Timed out posting to {url}"))?
+        .wrap_err_with(|| format!("Failed to post to {url}"))?;
+
+    let status_line = String::from_utf8_lossy(&response)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => bail!("{url} responded with `{status_line}`"),
+    }
+}
+
+async fn append_file(path: &PathBuf, event: &AlertEvent, time: &str) -> Result<(), Report> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+
+    let line = format!("{time} {}\n", event.describe());
+    file.write_all(line.as_bytes()).await?;
+
+    Ok(())
+}
+
+impl SinkKind {
+    async fn send(&self, event: &AlertEvent, time: &str) -> Result<(), Report> {
+        match self {
+            Self::Command { command } => run_command(command, event, time).await,
+            Self::Webhook { url } => {
+                let body = serde_json::to_vec(&event_json(event, time))?;
+                http_post(url, "application/json", &body).await
+            }
+            Self::File { path } => append_file(path, event, time).await,
+        }
+    }
+}
+
+// (sink, rule, target)
+type DeliveryKey = (String, String, String);
+
+pub struct Notifier {
+    clock: SharedClock,
+    sinks: Vec<SinkConfig>,
+    // When each sink was last notified of a rule firing for a target
+    last_fired: HashMap<DeliveryKey, f64>,
+    // Firings a sink was notified of and hasn't seen resolve yet
+    unresolved: HashSet<DeliveryKey>,
+}
+
+impl Notifier {
+    pub fn new(sinks: Vec<SinkConfig>, clock: SharedClock) -> Self {
+        Self {
+            clock,
+            sinks,
+            last_fired: HashMap::new(),
+            unresolved: HashSet::new(),
+        }
+    }
+
+    // Firings are rate limited, and a resolve only goes out to sinks that saw the firing
+    fn should_send(&mut self, sink: &SinkConfig, event: &AlertEvent) -> bool {
+        let key = (
+            sink.name.clone(),
+            event.rule.name.clone(),
+            event.target.clone(),
+        );
+
+        match event.transition {
+            AlertTransition::Pending => false,
+            AlertTransition::Firing => {
+                if let Some(&last) = self.last_fired.get(&key) {
+                    if event.time - last < sink.rate_limit_secs {
+                        return false;
+                    }
+                }
+
+                self.last_fired.insert(key.clone(), event.time);
+                self.unresolved.insert(key);
+                true
+            }
+            AlertTransition::Resolved => self.unresolved.remove(&key),
+        }
+    }
+
+    // Spawns a send for every sink the event is routed to, so a slow webhook can't hold up
+    // alert evaluation
+    pub fn dispatch(&mut self, event: AlertEvent) {
+        let routed: Vec<SinkConfig> = self
+            .sinks
+            .clone()
+            .into_iter()
+            .filter(|x| event.rule.sinks.is_empty() || event.rule.sinks.contains(&x.name))
+            .filter(|x| self.should_send(x, &event))
+            .collect();
+
+        let time = funcs::rfc3339_fmt(self.clock.as_ref(), event.time);
+
+        for sink in routed {
+            let event = event.clone();
+            let time = time.clone();
+
+            tokio::spawn(async move {
+                if let Err(e) = sink.kind.send(&event, &time).await {
+                    tracing::error!("Alert sink {} failed: {e:?}", sink.name);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_http_url_defaults_port() {
+        let split = |url| split_http_url(url).unwrap();
+
+        assert_eq!(
+            split("http://host/hook"),
+            ("host:80".into(), "/hook".into())
+        );
+        assert_eq!(split("http://host:8080"), ("host:8080".into(), "/".into()));
+        assert_eq!(split("http://[::1]/a"), ("[::1]:80".into(), "/a".into()));
+        assert_eq!(
+            split("http://[::1]:9000/a"),
+            ("[::1]:9000".into(), "/a".into())
+        );
+    }
+
+    #[test]
+    fn split_http_url_rejects_https() {
+        assert!(split_http_url("https://hooks.example.com/x").is_err());
+        assert!(split_http_url("ftp://host/x").is_err());
+        assert!(split_http_url("http:///x").is_err());
+    }
+
+    #[tokio::test]
+    async fn http_post_reaches_local_listener() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            let mut buf = [0; 1024];
+
+            // The client keeps the connection open until it has read the response
+            while !String::from_utf8_lossy(&request).ends_with("{\"a\":1}") {
+                let n = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        http_post(&url, "application/json", br#"{"a":1}"#)
+            .await
+            .unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Type: application/json\r\n"));
+        assert!(request.contains("Content-Length: 7\r\n"));
+    }
+
+    #[tokio::test]
+    async fn http_post_fails_on_error_status() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 500 Internal Server Error\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        assert!(http_post(&url, "text/plain", b"x").await.is_err());
+    }
+
+    fn event(time: f64, transition: AlertTransition) -> AlertEvent {
+        AlertEvent {
+            time,
+            rule: crate::alert::AlertRule {
+                name: "slow".to_string(),
+                ..Default::default()
+            },
+            target: "host".to_string(),
+            transition,
+            value: None,
+        }
+    }
+
+    #[test]
+    fn resolve_follows_only_delivered_firings() {
+        let clock = crate::clock::SystemClock::shared();
+        let sink = SinkConfig {
+            name: "log".to_string(),
+            kind: SinkKind::File {
+                path: PathBuf::from("/dev/null"),
+            },
+            rate_limit_secs: 60.0,
+        };
+        let mut notifier = Notifier::new(vec![sink.clone()], clock);
+        let mut sent = |time, transition| notifier.should_send(&sink, &event(time, transition));
+
+        assert!(!sent(0.0, AlertTransition::Pending));
+        assert!(sent(1.0, AlertTransition::Firing));
+        assert!(sent(5.0, AlertTransition::Resolved));
+
+        // Flapping inside the rate limit stays quiet, resolve included
+        assert!(!sent(10.0, AlertTransition::Firing));
+        assert!(!sent(15.0, AlertTransition::Resolved));
+
+        assert!(sent(70.0, AlertTransition::Firing));
+        assert!(sent(75.0, AlertTransition::Resolved));
+        assert!(!sent(76.0, AlertTransition::Resolved));
+    }
+}
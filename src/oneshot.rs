@@ -0,0 +1,290 @@
+use crate::init::RunArgs;
+
+use egui_ping_graph::{
+    fmt_float_s, pingstat_from_rawdata,
+    probe::{self, ProbeRuntime},
+    series::{PingStatistics, Sample, SampleCursor},
+};
+
+use std::collections::{HashMap, HashSet};
+
+const DEFAULT_COUNT: usize = 10;
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+// Extra time given to slow targets on top of count * ping interval
+const COUNT_GRACE_SECS: f64 = 10.0;
+
+pub struct Summary {
+    pub target: String,
+    pub sent: usize,
+    pub received: usize,
+    pub stats: PingStatistics,
+}
+
+impl Summary {
+    fn new(target: &str, samples: &[Sample]) -> Self {
+        let raw: Vec<_> = samples
+            .iter()
+            .map(|x| x.rtt.map(|rtt| (x.time, rtt)))
+            .collect();
+
+        Self {
+            target: target.to_string(),
+            sent: samples.len(),
+            received: samples.iter().filter(|x| x.rtt.is_some()).count(),
+            stats: pingstat_from_rawdata(&raw),
+        }
+    }
+
+    pub fn loss(&self) -> Option<f64> {
+        (self.sent > 0).then(|| (self.sent - self.received) as f64 / self.sent as f64 * 100.0)
+    }
+
+    // Returns a description of every exceeded threshold, no samples at all count as one
+    fn violations(&self, args: &RunArgs) -> Vec<String> {
+        let Some(loss) = self.loss() else {
+            return vec!["no data".to_string()];
+        };
+
+        let mut violations = vec![];
+
+        if let Some(max) = args.max_loss.filter(|&max| loss > max) {
+            violations.push(format!("loss {loss:.1}% > {max}%"));
+        }
+
+        let rtt_checks = [
+            ("avg", self.stats.avg, args.max_avg),
+            ("p95", self.stats.p95, args.max_p95),
+            ("jitter", self.stats.jitter, args.max_jitter),
+        ];
+        for (label, value, max) in rtt_checks {
+            let Some(max) = max else {
+                continue;
+            };
+
+            // NaN when nothing came back, which only the loss threshold should judge
+            if value > max {
+                violations.push(format!(
+                    "{label} {} > {}",
+                    fmt_float_s(value),
+                    fmt_float_s(max)
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+// Repeated targets share one probe, so each is only collected and summarized once
+fn unique_targets(targets: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    targets
+        .iter()
+        .filter(|x| seen.insert(x.as_str()))
+        .cloned()
+        .collect()
+}
+
+// Collects samples from all targets until each has `count` of them or time runs out
+pub fn collect(
+    probes: &ProbeRuntime,
+    targets: &[String],
+    count: Option<usize>,
+    duration: Option<f64>,
+) -> Vec<Summary> {
+    let count = match (count, duration) {
+        (None, None) => Some(DEFAULT_COUNT),
+        _ => count,
+    };
+    let deadline = duration.unwrap_or(f64::INFINITY).min(
+        count
+            .map(|x| x as f64 * probe::PING_INTERVAL.as_secs_f64() + COUNT_GRACE_SECS)
+            .unwrap_or(f64::INFINITY),
+    );
+
+    let targets = unique_targets(targets);
+    for target in &targets {
+        probes.add_target(target);
+    }
+
+    let clock = probes.clock();
+    let get_sec_elapsed = || clock.elapsed();
+    let started = get_sec_elapsed();
+
+    let mut samples: HashMap<String, Vec<Sample>> = HashMap::new();
+    let mut cursor = SampleCursor::default();
+
+    let collecting = async {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Ok(ts_hash) = probes.timeseries.lock() {
+                for (target, series) in ts_hash.iter() {
+                    samples
+                        .entry(target.clone())
+                        .or_default()
+                        .extend(cursor.read_new(target, series));
+                }
+            }
+
+            let done = count.is_some_and(|count| {
+                targets
+                    .iter()
+                    .all(|x| samples.get(x).is_some_and(|x| x.len() >= count))
+            });
+
+            if done || get_sec_elapsed() - started >= deadline {
+                break;
+            }
+        }
+    };
+
+    probes.block_on(async {
+        tokio::select! {
+            _ = collecting => {},
+            // Still summarize whatever was collected so far
+            _ = tokio::signal::ctrl_c() => {},
+        }
+    });
+
+    targets
+        .iter()
+        .map(|target| {
+            let mut samples = samples.remove(target).unwrap_or_default();
+            if let Some(count) = count {
+                samples.truncate(count);
+            }
+            Summary::new(target, &samples)
+        })
+        .collect()
+}
+
+fn fmt_stat(summary: &Summary, val: f64) -> String {
+    if summary.received == 0 {
+        "-".to_string()
+    } else {
+        fmt_float_s(val)
+    }
+}
+
+// Prints the summary table and returns whether every target stayed within the thresholds
+pub fn run(probes: &ProbeRuntime, args: &RunArgs) -> bool {
+    let summaries = collect(probes, &args.targets, args.count, args.duration);
+
+    let width = summaries
+        .iter()
+        .map(|x| x.target.len())
+        .max()
+        .unwrap_or(0)
+        .max("target".len());
+
+    println!(
+        "{:<width$} {:>5} {:>5} {:>6} {:>10} {:>10} {:>10} {:>10} {:>10}  status",
+        "target", "sent", "recv", "loss", "min", "avg", "max", "jitter", "p95"
+    );
+
+    let mut passed = true;
+
+    for summary in &summaries {
+        let violations = summary.violations(args);
+        passed &= violations.is_empty();
+
+        let loss = match summary.loss() {
+            Some(loss) => format!("{loss:.1}%"),
+            None => "-".to_string(),
+        };
+        let status = if violations.is_empty() {
+            "ok".to_string()
+        } else {
+            format!("FAIL {}", violations.join(", "))
+        };
+
+        println!(
+            "{:<width$} {:>5} {:>5} {:>6} {:>10} {:>10} {:>10} {:>10} {:>10}  {status}",
+            summary.target,
+            summary.sent,
+            summary.received,
+            loss,
+            fmt_stat(summary, summary.stats.min),
+            fmt_stat(summary, summary.stats.avg),
+            fmt_stat(summary, summary.stats.max),
+            fmt_stat(summary, summary.stats.jitter),
+            fmt_stat(summary, summary.stats.p95),
+        );
+    }
+
+    passed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> RunArgs {
+        RunArgs {
+            targets: vec![],
+            count: None,
+            duration: None,
+            max_loss: Some(10.0),
+            max_avg: Some(0.05),
+            max_p95: None,
+            max_jitter: Some(0.01),
+            config: None,
+        }
+    }
+
+    fn summary(sent: usize, received: usize, avg: f64, jitter: f64) -> Summary {
+        Summary {
+            target: "example.com".to_string(),
+            sent,
+            received,
+            stats: PingStatistics {
+                avg,
+                jitter,
+                p95: avg,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn deduplicates_targets_in_order() {
+        let targets = ["b", "a", "b", "c", "a"].map(String::from);
+        assert_eq!(unique_targets(&targets), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn reports_exceeded_thresholds() {
+        let args = args();
+
+        assert!(summary(10, 10, 0.02, 0.001).violations(&args).is_empty());
+        // Only going above a threshold fails
+        assert!(summary(10, 9, 0.05, 0.01).violations(&args).is_empty());
+
+        assert_eq!(
+            summary(10, 8, 0.08, 0.02).violations(&args),
+            [
+                "loss 20.0% > 10%",
+                "avg 80 ms > 50 ms",
+                "jitter 20 ms > 10 ms"
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_data_fails() {
+        let args = args();
+
+        assert_eq!(
+            summary(0, 0, f64::NAN, f64::NAN).violations(&args),
+            ["no data"]
+        );
+        // Without replies only the loss is judged
+        assert_eq!(
+            summary(5, 0, f64::NAN, f64::NAN).violations(&args),
+            ["loss 100.0% > 10%"]
+        );
+    }
+}
@@ -0,0 +1,384 @@
+pub use crate::{
+    alert::{AlertEngine, AlertEvent, AlertState, AlertTransition, FiringInterval},
+    app_pinger::PING_INTERVAL,
+};
+
+use crate::{
+    agent, app_agent, app_alerter, app_cleaner, app_control, app_exporter, app_metrics, app_pinger,
+    clock::{SharedClock, SystemClock},
+    config::Config,
+    control::Control,
+    events::EventLog,
+    metrics::Metrics,
+    notify::Notifier,
+    series::Series,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use color_eyre::eyre::{eyre, Report, WrapErr};
+use tokio::{
+    net::TcpListener,
+    runtime::{Handle, Runtime},
+    task::JoinHandle,
+};
+
+/// Trims a target, refusing anything ping could read as an option or that could split into
+/// several arguments
+pub fn parse_target(target: &str) -> Result<String, String> {
+    let target = target.trim();
+
+    if target.is_empty() {
+        Err("Target is empty".to_string())
+    } else if target.starts_with('-') {
+        Err(format!("Target `{target}` can't start with -"))
+    } else if target.chars().any(|x| x.is_whitespace() || x.is_control()) {
+        Err(format!(
+            "Target `{target}` contains whitespace or control characters"
+        ))
+    } else {
+        Ok(target.to_string())
+    }
+}
+
+/// Wakes the UI when new data arrives, at most once per frame interval
+#[derive(Clone, Default)]
+pub struct Repainter {
+    target: Arc<OnceLock<(egui::Context, Duration)>>,
+}
+
+impl Repainter {
+    pub fn attach(&self, ctx: egui::Context, frame_interval: Duration) {
+        self.target.set((ctx, frame_interval)).ok();
+    }
+
+    pub fn request(&self) {
+        if let Some((ctx, frame_interval)) = self.target.get() {
+            ctx.request_repaint_after(*frame_interval);
+        }
+    }
+}
+
+// Ping threads block on ping's output and never finish, so waiting for them the way a
+// dropped runtime does would hang on exit
+struct BackgroundRuntime(Option<Runtime>);
+
+impl std::ops::Deref for BackgroundRuntime {
+    type Target = Runtime;
+
+    fn deref(&self) -> &Runtime {
+        self.0.as_ref().expect("runtime is only taken on drop")
+    }
+}
+
+impl Drop for BackgroundRuntime {
+    fn drop(&mut self) {
+        if let Some(rt) = self.0.take() {
+            rt.shutdown_background();
+        }
+    }
+}
+
+struct ProbeTasks {
+    // Order the target was added in, doubles as its palette index
+    order: usize,
+    stop: Arc<AtomicBool>,
+    pinger: JoinHandle<Result<(), Report>>,
+    cleaner: JoinHandle<Result<(), Report>>,
+}
+
+/// Starts and stops the per target tasks, shared by the UI and the control API
+pub struct TargetManager {
+    handle: Handle,
+    clock: SharedClock,
+    timeseries: Arc<Mutex<HashMap<String, Series>>>,
+    events: Arc<Mutex<EventLog>>,
+    repainter: Repainter,
+
+    tasks: Mutex<HashMap<String, ProbeTasks>>,
+    // Palette index handed to the next new target
+    next_color: Mutex<usize>,
+}
+
+impl TargetManager {
+    pub fn targets(&self) -> Vec<String> {
+        let Ok(tasks) = self.tasks.lock() else {
+            return vec![];
+        };
+
+        let mut targets: Vec<_> = tasks.iter().collect();
+        targets.sort_by_key(|x| x.1.order);
+        targets.into_iter().map(|x| x.0.clone()).collect()
+    }
+
+    pub fn next_color(&self) -> usize {
+        match self.next_color.lock() {
+            Ok(mut next_color) => {
+                *next_color += 1;
+                *next_color - 1
+            }
+            Err(_) => 0,
+        }
+    }
+
+    /// Returns false when the target was already probed
+    pub fn add(&self, target: &str) -> Result<bool, String> {
+        let target = &parse_target(target)?;
+        let Ok(mut tasks) = self.tasks.lock() else {
+            return Ok(false);
+        };
+
+        if tasks.contains_key(target) {
+            return Ok(false);
+        }
+
+        let idxcolor = self.next_color();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let pinger = self.handle.spawn(app_pinger::run_pinger_supervised(
+            target.to_string(),
+            self.clock.clone(),
+            self.timeseries.clone(),
+            idxcolor,
+            self.events.clone(),
+            self.repainter.clone(),
+            stop.clone(),
+        ));
+        let cleaner = self.handle.spawn(app_cleaner::run_cleaner(
+            target.to_string(),
+            self.clock.clone(),
+            self.timeseries.clone(),
+        ));
+
+        tasks.insert(
+            target.to_string(),
+            ProbeTasks {
+                order: idxcolor,
+                stop,
+                pinger,
+                cleaner,
+            },
+        );
+
+        Ok(true)
+    }
+
+    /// Returns false when the target wasn't probed
+    pub fn remove(&self, target: &str) -> bool {
+        let probe = self.tasks.lock().ok().and_then(|mut x| x.remove(target));
+
+        if let Some(probe) = &probe {
+            // The blocking ping thread can't be aborted, it exits on its next result instead
+            probe.stop.store(true, Ordering::Relaxed);
+            probe.pinger.abort();
+            probe.cleaner.abort();
+        }
+
+        if let Ok(mut ts_hash) = self.timeseries.lock() {
+            ts_hash.remove(target);
+        }
+
+        self.repainter.request();
+
+        probe.is_some()
+    }
+}
+
+/// Owns the tokio runtime running pingers, cleaners and the alerter, and the shared state
+/// they write into
+pub struct ProbeRuntime {
+    rt: BackgroundRuntime,
+    clock: SharedClock,
+
+    pub timeseries: Arc<Mutex<HashMap<String, Series>>>,
+    pub alerts: Arc<Mutex<AlertEngine>>,
+    pub events: Arc<Mutex<EventLog>>,
+    pub repainter: Repainter,
+    pub targets: Arc<TargetManager>,
+}
+
+impl ProbeRuntime {
+    /// Starts the background tasks the config asks for, targets are added afterwards
+    pub fn start(config: Config) -> Result<Self, Report> {
+        Self::start_with_clock(config, SystemClock::shared())
+    }
+
+    /// Like `start`, with everything time dependent reading the given clock, so a manual one
+    /// makes runs reproducible
+    pub fn start_with_clock(config: Config, clock: SharedClock) -> Result<Self, Report> {
+        let rt = Runtime::new()?;
+
+        let timeseries = Arc::new(Mutex::new(HashMap::new()));
+        let events = Arc::new(Mutex::new(EventLog::new(clock.clone())));
+        let repainter = Repainter::default();
+
+        let targets = Arc::new(TargetManager {
+            handle: rt.handle().clone(),
+            clock: clock.clone(),
+            timeseries: timeseries.clone(),
+            events: events.clone(),
+            repainter: repainter.clone(),
+            tasks: Mutex::new(HashMap::new()),
+            next_color: Mutex::new(0),
+        });
+
+        let probes = Self {
+            rt: BackgroundRuntime(Some(rt)),
+            clock: clock.clone(),
+            timeseries,
+            alerts: Arc::new(Mutex::new(AlertEngine::with_rules(config.alerts))),
+            events,
+            repainter,
+            targets,
+        };
+
+        probes.rt.spawn(app_alerter::run_alerter(
+            clock.clone(),
+            probes.timeseries.clone(),
+            probes.alerts.clone(),
+            Notifier::new(config.sinks, clock.clone()),
+            probes.events.clone(),
+        ));
+
+        for agent in config.agents {
+            tracing::info!("Showing targets of agent {}", agent.addr);
+            probes.rt.spawn(app_agent::run_agent_client(
+                agent,
+                clock.clone(),
+                probes.timeseries.clone(),
+                probes.events.clone(),
+                probes.repainter.clone(),
+                probes.targets.clone(),
+            ));
+        }
+
+        for exporter in config.exporters {
+            tracing::info!("Pushing samples to {}", exporter.describe());
+            probes.rt.spawn(app_exporter::run_exporter(
+                probes.timeseries.clone(),
+                exporter,
+                clock.clone(),
+            ));
+        }
+
+        if let Some(addr) = config.metrics_listen {
+            // Bound up front so a taken port fails startup instead of a background task
+            let listener = probes
+                .rt
+                .block_on(tokio::net::TcpListener::bind(addr))
+                .wrap_err_with(|| format!("Failed to listen for metrics on {addr}"))?;
+            let metrics = Arc::new(Mutex::new(Metrics::default()));
+
+            probes.rt.spawn(app_metrics::run_metrics_collector(
+                clock.clone(),
+                probes.timeseries.clone(),
+                probes.events.clone(),
+                metrics.clone(),
+            ));
+            probes
+                .rt
+                .spawn(app_metrics::run_metrics_server(listener, metrics));
+
+            tracing::info!("Serving metrics on http://{addr}/metrics");
+        }
+
+        if config.control_socket.is_some() || config.control_listen.is_some() {
+            probes.start_control(config.control_socket.as_deref(), config.control_listen)?;
+        }
+
+        Ok(probes)
+    }
+
+    fn start_control(
+        &self,
+        socket: Option<&std::path::Path>,
+        listen: Option<std::net::SocketAddr>,
+    ) -> Result<(), Report> {
+        let control = Arc::new(Control {
+            clock: self.clock.clone(),
+            timeseries: self.timeseries.clone(),
+            events: self.events.clone(),
+            targets: self.targets.clone(),
+        });
+
+        if let Some(addr) = listen {
+            // The API can start pingers, so it stays off the network
+            if !addr.ip().is_loopback() {
+                return Err(eyre!(
+                    "Control API may only listen on a loopback address, not {addr}"
+                ));
+            }
+
+            let listener = self
+                .rt
+                .block_on(tokio::net::TcpListener::bind(addr))
+                .wrap_err_with(|| format!("Failed to listen for control on {addr}"))?;
+            self.rt
+                .spawn(app_control::run_control_tcp(listener, control.clone()));
+
+            tracing::info!("Control API listening on {addr}");
+        }
+
+        if let Some(path) = socket {
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+                // Left behind by an earlier run that didn't shut down cleanly
+                if std::fs::symlink_metadata(path).is_ok_and(|x| x.file_type().is_socket()) {
+                    std::fs::remove_file(path).ok();
+                }
+
+                let listener = {
+                    let _guard = self.rt.enter();
+                    tokio::net::UnixListener::bind(path)
+                }
+                .wrap_err_with(|| format!("Failed to listen for control on {}", path.display()))?;
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+
+                self.rt
+                    .spawn(app_control::run_control_unix(listener, control));
+
+                tracing::info!("Control API listening on {}", path.display());
+            }
+
+            #[cfg(not(unix))]
+            return Err(eyre!(
+                "Control sockets are not supported on this platform, use control_listen instead ({})",
+                path.display()
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn clock(&self) -> SharedClock {
+        self.clock.clone()
+    }
+
+    /// Streams every sample to viewers connecting to the listener, until it fails
+    pub async fn serve_viewers(&self, listener: TcpListener, name: String) -> Result<(), Report> {
+        agent::serve(listener, name, self.timeseries.clone(), self.clock()).await
+    }
+
+    pub fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        self.rt.block_on(future)
+    }
+
+    pub fn add_target(&self, target: &str) {
+        if let Err(e) = self.targets.add(target) {
+            tracing::warn!("Not probing {target}: {e}");
+        }
+    }
+
+    pub fn remove_target(&self, target: &str) {
+        self.targets.remove(target);
+    }
+}
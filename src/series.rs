@@ -1,11 +1,19 @@
+pub use crate::anomaly::{Anomaly, AnomalyDetector, AnomalyKind};
+
 use crate::funcs;
 
+use std::collections::HashMap;
+
 #[derive(Clone)]
 pub struct Series {
     pub raw: Vec<Option<(f64, f64)>>,
+    pub timeout_at: Vec<f64>,
     pub linecol: egui::Color32,
-    pub linecol_idx: u8,
+    pub linecol_idx: usize,
     pub stats: PingStatistics,
+
+    pub detector: AnomalyDetector,
+    pub anomalies: Vec<Anomaly>,
 }
 
 #[derive(Clone, Default)]
@@ -23,23 +31,37 @@ impl Default for Series {
     fn default() -> Self {
         Self {
             raw: vec![],
+            timeout_at: vec![],
             linecol: egui::Color32::RED,
             linecol_idx: 0,
 
             stats: Default::default(),
+
+            detector: Default::default(),
+            anomalies: vec![],
         }
     }
 }
 
 // #[allow(dead_code)]
 impl Series {
-    pub fn with_idxcolor(idx: u8) -> Self {
+    pub fn with_idxcolor(idx: usize) -> Self {
         Self {
             linecol_idx: idx,
             ..Default::default()
         }
     }
 
+    pub fn push(&mut self, value: Option<(f64, f64)>) -> Option<Anomaly> {
+        self.raw.push(value);
+
+        let (time, latency) = value?;
+        let anomaly = self.detector.push(time, latency)?;
+        self.anomalies.push(anomaly);
+
+        Some(anomaly)
+    }
+
     pub fn get_younger_than(&self, time: f64) -> &[Option<(f64, f64)>] {
         let split_pos = self.raw.iter().position(|x| {
             if let Some((x, _)) = x {
@@ -49,11 +71,8 @@ impl Series {
             }
         });
 
-        if let Some(pos) = split_pos {
-            self.raw.split_at(pos).1
-        } else {
-            self.raw.as_slice()
-        }
+        // Nothing younger means the whole series has aged out
+        self.raw.split_at(split_pos.unwrap_or(self.raw.len())).1
     }
 
     pub fn get_splitted(&self) -> Vec<Vec<(f64, f64)>> {
@@ -63,14 +82,6 @@ impl Series {
             .collect()
     }
 
-    pub fn get_highest_value(&self) -> f64 {
-        self.raw
-            .iter()
-            .filter_map(|x| x.map(|x| x.1))
-            .max_by(|a, b| a.total_cmp(b))
-            .unwrap()
-    }
-
     pub fn get_highest_value_youngerthan(&self, time: f64) -> f64 {
         self.get_younger_than(time)
             .iter()
@@ -87,19 +98,60 @@ impl Series {
         self.stats = self.get_pingstat();
     }
 
-    pub fn splitted_to_plotpoints(&self) -> Vec<egui_plot::PlotPoints> {
+    pub fn values_younger_than(&self, time: f64) -> impl Iterator<Item = f64> + '_ {
+        self.get_younger_than(time)
+            .iter()
+            .filter_map(|x| x.map(|x| x.1))
+    }
+
+    pub fn splitted_to_plotpoints(&self, y_map: impl Fn(f64) -> f64) -> Vec<egui_plot::PlotPoints> {
         self.get_splitted()
             .into_iter()
             .map(|x| {
                 egui_plot::PlotPoints::Owned(Vec::from_iter(
                     x.iter()
-                        .map(|&(x, y)| egui_plot::PlotPoint::from([x, y]))
+                        .map(|&(x, y)| egui_plot::PlotPoint::from([x, y_map(y)]))
                         .collect::<Vec<_>>(),
                 ))
             })
             .collect()
     }
 
+    // Replies newer than `reply_time` and timeouts newer than `timeout_time`, sorted by time
+    fn samples_after(&self, reply_time: f64, timeout_time: f64) -> Vec<Sample> {
+        let mut samples: Vec<_> = self
+            .raw
+            .iter()
+            .flatten()
+            .filter(|x| x.0 > reply_time)
+            .map(|&(time, rtt)| Sample {
+                time,
+                rtt: Some(rtt),
+            })
+            .chain(
+                self.timeout_at
+                    .iter()
+                    .filter(|&&x| x > timeout_time)
+                    .map(|&time| Sample { time, rtt: None }),
+            )
+            .collect();
+        samples.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        samples
+    }
+
+    pub fn samples_younger_than(&self, time: f64) -> Vec<Sample> {
+        self.samples_after(time, time)
+    }
+
+    // Time of the newest reply or timeout
+    pub fn last_sample_time(&self) -> Option<f64> {
+        let reply = self.raw.iter().rev().flatten().next().map(|x| x.0);
+        let timeout = self.timeout_at.last().copied();
+
+        reply.into_iter().chain(timeout).reduce(f64::max)
+    }
+
     pub fn remove_older_than(&mut self, time: f64) {
         let split_pos = self.raw.iter().position(|x| {
             if let Some((x, _)) = x {
@@ -109,8 +161,58 @@ impl Series {
             }
         });
 
-        if let Some(pos) = split_pos {
-            self.raw = self.raw.split_at(pos).1.to_vec();
+        self.raw = self.raw.split_off(split_pos.unwrap_or(self.raw.len()));
+
+        self.timeout_at.retain(|&x| x > time);
+        self.anomalies.retain(|x| x.time > time);
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Sample {
+    pub time: f64,
+    // None for a timeout
+    pub rtt: Option<f64>,
+}
+
+// Remembers how far each target's series has been read so consumers see every sample once,
+// series only keep a sliding window so they have to be read more often than it moves
+#[derive(Default)]
+pub struct SampleCursor {
+    // Skips everything up to this time for targets not seen yet
+    start: Option<f64>,
+    seen: HashMap<String, (f64, f64)>,
+}
+
+impl SampleCursor {
+    // Returns the samples and timeouts added since the last call, ordered by time
+    pub fn read_new(&mut self, target: &str, series: &Series) -> Vec<Sample> {
+        let start = self.start.unwrap_or(f64::NEG_INFINITY);
+        let (seen_sample, seen_timeout) = self
+            .seen
+            .entry(target.to_string())
+            .or_insert((start, start));
+
+        let samples = series.samples_after(*seen_sample, *seen_timeout);
+
+        for sample in &samples {
+            match sample.rtt {
+                Some(_) => *seen_sample = sample.time,
+                None => *seen_timeout = sample.time,
+            }
+        }
+
+        samples
+    }
+
+    pub fn starting_at(time: f64) -> Self {
+        Self {
+            start: Some(time),
+            ..Default::default()
         }
     }
+
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.seen.retain(|target, _| keep(target));
+    }
 }
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use yata::core::Method;
+use yata::methods::{EMA, SMA, SMM, WMA};
+
+type Filter = Box<dyn Method<Params = u8, Input = f64, Output = f64>>;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SmoothingFilter {
+    Sma,
+    Ema,
+    Wma,
+    Median,
+}
+
+impl SmoothingFilter {
+    pub const ALL: [SmoothingFilter; 4] = [Self::Sma, Self::Ema, Self::Wma, Self::Median];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Sma => "SMA",
+            Self::Ema => "EMA",
+            Self::Wma => "WMA",
+            Self::Median => "Median",
+        }
+    }
+
+    fn build(&self, window: u8, initial: f64) -> Option<Filter> {
+        let window = window.max(1);
+
+        match self {
+            Self::Sma => SMA::new(window, &initial)
+                .ok()
+                .map(|x| Box::new(x) as Filter),
+            Self::Ema => EMA::new(window, &initial)
+                .ok()
+                .map(|x| Box::new(x) as Filter),
+            Self::Wma => WMA::new(window, &initial)
+                .ok()
+                .map(|x| Box::new(x) as Filter),
+            Self::Median => SMM::new(window, &initial)
+                .ok()
+                .map(|x| Box::new(x) as Filter),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmoothingSettings {
+    pub enabled: bool,
+    pub filter: SmoothingFilter,
+    pub window: u8,
+}
+
+impl Default for SmoothingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            filter: SmoothingFilter::Sma,
+            window: 8,
+        }
+    }
+}
+
+// Runs the filter over a contiguous run of samples, seeded with the first value so the
+// overlay doesn't ramp up from zero
+pub fn smooth(settings: &SmoothingSettings, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let Some(&(_, first)) = points.first() else {
+        return vec![];
+    };
+
+    let Some(mut filter) = settings.filter.build(settings.window, first) else {
+        return vec![];
+    };
+
+    points.iter().map(|&(x, y)| (x, filter.next(&y))).collect()
+}
+
+pub fn show_options(
+    ui: &mut egui::Ui,
+    targets: &[String],
+    settings: &mut std::collections::HashMap<String, SmoothingSettings>,
+) {
+    egui::Grid::new("smoothing_opts").show(ui, |ui| {
+        for target in targets {
+            let entry = settings.entry(target.clone()).or_default();
+
+            ui.checkbox(&mut entry.enabled, target);
+
+            egui::ComboBox::from_id_source(("smoothing_filter", target))
+                .selected_text(entry.filter.label())
+                .show_ui(ui, |ui| {
+                    for filter in SmoothingFilter::ALL {
+                        ui.selectable_value(&mut entry.filter, filter, filter.label());
+                    }
+                });
+
+            ui.add(egui::Slider::new(&mut entry.window, 2..=64).text("samples"));
+            ui.end_row();
+        }
+    });
+}
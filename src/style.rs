@@ -0,0 +1,107 @@
+use crate::funcs;
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+// Spacing hues by the golden angle keeps any number of consecutive colors apart
+const GOLDEN_ANGLE: f64 = 137.507_764;
+
+pub fn palette_color(idx: usize) -> egui::Color32 {
+    // Start from the hue of the original #48B7B2 teal
+    let hue = (177.0 + idx as f64 * GOLDEN_ANGLE) % 360.0;
+    // Alternate lightness a bit so neighbouring hues on big palettes still differ
+    let lightness = if (idx / 3).is_multiple_of(2) {
+        0.5
+    } else {
+        0.62
+    };
+
+    let (r, g, b) = funcs::hsl_to_rgb(hue, 0.45, lightness);
+    egui::Color32::from_rgb(r, g, b)
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DashStyle {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl DashStyle {
+    pub const ALL: [DashStyle; 3] = [Self::Solid, Self::Dashed, Self::Dotted];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Solid => "Solid",
+            Self::Dashed => "Dashed",
+            Self::Dotted => "Dotted",
+        }
+    }
+
+    pub fn line_style(&self) -> egui_plot::LineStyle {
+        match self {
+            Self::Solid => egui_plot::LineStyle::Solid,
+            Self::Dashed => egui_plot::LineStyle::dashed_dense(),
+            Self::Dotted => egui_plot::LineStyle::dotted_dense(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct TargetStyle {
+    pub visible: bool,
+    pub color: egui::Color32,
+    pub width: f32,
+    pub dash: DashStyle,
+}
+
+impl TargetStyle {
+    pub fn with_color(color: egui::Color32) -> Self {
+        Self {
+            visible: true,
+            color,
+            width: 3.0,
+            dash: DashStyle::Solid,
+        }
+    }
+}
+
+// Only targets in `removable` get a button to stop them, the others come from agents
+pub fn show_options(
+    ui: &mut egui::Ui,
+    targets: &[String],
+    removable: &[String],
+    styles: &mut HashMap<String, TargetStyle>,
+) -> Option<String> {
+    let mut removed = None;
+
+    egui::Grid::new("target_styles").show(ui, |ui| {
+        for target in targets {
+            let Some(style) = styles.get_mut(target) else {
+                continue;
+            };
+
+            ui.checkbox(&mut style.visible, target);
+            ui.color_edit_button_srgba(&mut style.color);
+            ui.add(egui::Slider::new(&mut style.width, 0.5..=6.0).text("width"));
+
+            egui::ComboBox::from_id_source(("target_dash", target))
+                .selected_text(style.dash.label())
+                .show_ui(ui, |ui| {
+                    for dash in DashStyle::ALL {
+                        ui.selectable_value(&mut style.dash, dash, dash.label());
+                    }
+                });
+
+            if removable.contains(target)
+                && ui.small_button("✖").on_hover_text("Stop pinging").clicked()
+            {
+                removed = Some(target.clone());
+            }
+            ui.end_row();
+        }
+    });
+
+    removed
+}
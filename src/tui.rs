@@ -0,0 +1,331 @@
+use egui_ping_graph::{
+    clock::Clock,
+    events::EventLog,
+    fmt_float_s,
+    probe::ProbeRuntime,
+    series::{PingStatistics, Series},
+    style, x_axis_fmt, y_axis_fmt,
+};
+
+use std::time::Duration;
+
+use color_eyre::eyre::Report;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    prelude::*,
+    widgets::{Axis, Block, Borders, Cell, Chart, Dataset, GraphType, List, ListItem, Row, Table},
+};
+
+const TICK: Duration = Duration::from_millis(250);
+const EVENT_ROWS: u16 = 8;
+const WINDOW_STEP: f64 = 5.0;
+
+// Restores the terminal even when drawing bails out with an error
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        disable_raw_mode().ok();
+        execute!(std::io::stdout(), LeaveAlternateScreen).ok();
+    }
+}
+
+// Copy of what's drawn for one target, so no lock is held while drawing
+struct TargetView {
+    target: String,
+    color: Color,
+    segments: Vec<Vec<(f64, f64)>>,
+    timeouts: Vec<(f64, f64)>,
+    y_max: f64,
+    stats: PingStatistics,
+    firing: String,
+}
+
+impl TargetView {
+    fn new(target: &str, series: &Series, time_range: (f64, f64)) -> Self {
+        let color = style::palette_color(series.linecol_idx);
+
+        Self {
+            target: target.to_string(),
+            color: Color::Rgb(color.r(), color.g(), color.b()),
+            segments: series
+                .get_splitted()
+                .into_iter()
+                .map(|x| x.into_iter().filter(|x| x.0 >= time_range.0).collect())
+                .filter(|x: &Vec<_>| !x.is_empty())
+                .collect(),
+            timeouts: series
+                .timeout_at
+                .iter()
+                .filter(|&&x| x >= time_range.0)
+                .map(|&x| (x, 0.0))
+                .collect(),
+            y_max: series
+                .get_highest_value_youngerthan(time_range.0)
+                .max(0.001)
+                * 1.1,
+            stats: series.stats.clone(),
+            firing: String::new(),
+        }
+    }
+}
+
+// Takes each lock only long enough to copy the targets and events out
+fn snapshot(
+    probes: &ProbeRuntime,
+    time_range: (f64, f64),
+) -> (Vec<TargetView>, Vec<ListItem<'static>>) {
+    let mut targets: Vec<_> = match probes.timeseries.lock() {
+        Ok(ts_hash) => ts_hash
+            .iter()
+            .map(|(target, series)| TargetView::new(target, series, time_range))
+            .collect(),
+        Err(_) => vec![],
+    };
+    targets.sort_by(|a, b| a.target.cmp(&b.target));
+
+    if let Ok(alerts) = probes.alerts.lock() {
+        for view in &mut targets {
+            view.firing = alerts.firing_for(&view.target).join(", ");
+        }
+    }
+
+    let events = match probes.events.lock() {
+        Ok(events) => events_list(&events),
+        Err(_) => vec![],
+    };
+
+    (targets, events)
+}
+
+fn draw_chart(
+    frame: &mut Frame,
+    area: Rect,
+    view: &TargetView,
+    time_range: (f64, f64),
+    clock: &dyn Clock,
+) {
+    let mut datasets: Vec<_> = view
+        .segments
+        .iter()
+        .map(|x| {
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(view.color))
+                .data(x)
+        })
+        .collect();
+    datasets.push(
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&view.timeouts),
+    );
+
+    let chart = Chart::new(datasets)
+        .block(Block::default().borders(Borders::ALL).title(Span::styled(
+            view.target.as_str(),
+            Style::default().fg(view.color),
+        )))
+        .x_axis(
+            Axis::default()
+                .bounds([time_range.0, time_range.1])
+                .labels(vec![
+                    x_axis_fmt(clock, time_range.0).into(),
+                    x_axis_fmt(clock, time_range.1).into(),
+                ]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, view.y_max])
+                .labels(vec!["0".into(), y_axis_fmt(view.y_max).into()]),
+        );
+
+    frame.render_widget(chart, area);
+}
+
+fn draw_details(frame: &mut Frame, area: Rect, targets: &[TargetView]) {
+    let rows = targets.iter().map(|view| {
+        let stats = &view.stats;
+
+        let row = Row::new(vec![
+            Cell::from(view.target.as_str()).style(Style::default().fg(view.color)),
+            Cell::from(fmt_float_s(stats.last)),
+            Cell::from(fmt_float_s(stats.min)),
+            Cell::from(fmt_float_s(stats.max)),
+            Cell::from(fmt_float_s(stats.avg)),
+            Cell::from(fmt_float_s(stats.jitter)),
+            Cell::from(fmt_float_s(stats.p95)),
+            Cell::from(stats.timeouts.to_string()),
+            Cell::from(if view.firing.is_empty() {
+                "".to_string()
+            } else {
+                format!("⚠ {}", view.firing)
+            }),
+        ]);
+
+        if view.firing.is_empty() {
+            row
+        } else {
+            row.style(Style::default().fg(Color::Red))
+        }
+    });
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Min(16),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Min(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "target", "last", "min", "max", "avg", "jtr", "p95", "t/o", "alerts",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Details"));
+
+    frame.render_widget(table, area);
+}
+
+// The newest events first, as many as fit into the events box
+fn events_list(events: &EventLog) -> Vec<ListItem<'static>> {
+    events
+        .iter()
+        .rev()
+        .take(EVENT_ROWS as usize)
+        .map(|event| {
+            let line = format!(
+                "{} {} {} {}",
+                x_axis_fmt(events.clock(), event.time),
+                event.target,
+                event.kind.label(),
+                event.kind.details(&event.target, events.clock())
+            );
+
+            if event.kind.is_problem() {
+                ListItem::new(line).style(Style::default().fg(Color::Red))
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect()
+}
+
+fn draw(
+    frame: &mut Frame,
+    clock: &dyn Clock,
+    targets: &[TargetView],
+    events: Vec<ListItem>,
+    time_range: (f64, f64),
+    history_window: f64,
+) {
+    let [charts_area, details_area, events_area, help_area] = *Layout::vertical([
+        Constraint::Min(6),
+        Constraint::Length(targets.len() as u16 + 3),
+        Constraint::Length(EVENT_ROWS + 2),
+        Constraint::Length(1),
+    ])
+    .split(frame.size()) else {
+        return;
+    };
+
+    if !targets.is_empty() {
+        let chart_areas = Layout::vertical(
+            targets
+                .iter()
+                .map(|_| Constraint::Ratio(1, targets.len() as u32)),
+        )
+        .split(charts_area);
+
+        for (view, area) in targets.iter().zip(chart_areas.iter()) {
+            draw_chart(frame, *area, view, time_range, clock);
+        }
+    }
+
+    draw_details(frame, details_area, targets);
+
+    frame.render_widget(
+        List::new(events).block(Block::default().borders(Borders::ALL).title("Events")),
+        events_area,
+    );
+
+    frame.render_widget(
+        Line::from(format!("q quit  +/- window ({}s)", history_window.round()))
+            .style(Style::default().add_modifier(Modifier::DIM)),
+        help_area,
+    );
+}
+
+// Renders the probes into the terminal until q, Esc or Ctrl+C
+pub fn run(probes: &ProbeRuntime, targets: &[String]) -> Result<(), Report> {
+    for target in targets {
+        probes.add_target(target);
+    }
+
+    enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+
+    let mut terminal = Terminal::new(CrosstermBackend::new(std::io::stdout()))?;
+    let mut history_window = 10.0;
+    let clock = probes.clock();
+
+    loop {
+        let now = clock.elapsed();
+        let time_range = (
+            now - egui_ping_graph::DEFAULT_OFFSET - history_window,
+            now - egui_ping_graph::DEFAULT_OFFSET,
+        );
+
+        let (targets, events) = snapshot(probes, time_range);
+        terminal.draw(|frame| {
+            draw(
+                frame,
+                clock.as_ref(),
+                &targets,
+                events,
+                time_range,
+                history_window,
+            )
+        })?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Char('+') => {
+                history_window =
+                    (history_window + WINDOW_STEP).min(egui_ping_graph::MAX_HISTORY_SECS)
+            }
+            KeyCode::Char('-') => history_window = (history_window - WINDOW_STEP).max(WINDOW_STEP),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use crate::alert::{AlertEngine, AlertMetric, AlertRule, AlertState, Comparison};
+
+fn threshold_editor(ui: &mut egui::Ui, rule: &mut AlertRule) {
+    // Edit latency/jitter in ms and loss in percent, store in seconds and fraction
+    let (scale, suffix) = match rule.metric {
+        AlertMetric::Latency | AlertMetric::Jitter => (1000.0, " ms"),
+        AlertMetric::Loss => (100.0, " %"),
+    };
+
+    let mut val = rule.threshold * scale;
+    ui.add(
+        egui::DragValue::new(&mut val)
+            .clamp_range(0.0..=f64::MAX)
+            .suffix(suffix),
+    );
+    rule.threshold = val / scale;
+}
+
+pub fn show_options(ui: &mut egui::Ui, engine: &mut AlertEngine, draft: &mut AlertRule) {
+    let mut removed = None;
+
+    egui::Grid::new("alert_rules").show(ui, |ui| {
+        for rule in &engine.rules {
+            let firing = engine.states.iter().any(|((name, _), state)| {
+                *name == rule.name && matches!(state, AlertState::Firing { .. })
+            });
+
+            if firing {
+                ui.colored_label(egui::Color32::RED, &rule.name);
+            } else {
+                ui.label(&rule.name);
+            }
+            ui.label(rule.describe());
+
+            if ui.small_button("✖").clicked() {
+                removed = Some(rule.name.clone());
+            }
+            ui.end_row();
+        }
+    });
+
+    if let Some(name) = removed {
+        engine.remove_rule(&name);
+    }
+
+    ui.separator();
+
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Name");
+        ui.add(egui::TextEdit::singleline(&mut draft.name).desired_width(80.0));
+
+        let mut target = draft.target.clone().unwrap_or_default();
+        ui.label("Target");
+        ui.add(
+            egui::TextEdit::singleline(&mut target)
+                .hint_text("any")
+                .desired_width(80.0),
+        );
+        draft.target = (!target.is_empty()).then_some(target);
+
+        egui::ComboBox::from_id_source("alert_metric")
+            .selected_text(draft.metric.label())
+            .show_ui(ui, |ui| {
+                for metric in AlertMetric::ALL {
+                    ui.selectable_value(&mut draft.metric, metric, metric.label());
+                }
+            });
+
+        ui.selectable_value(&mut draft.comparison, Comparison::Above, ">");
+        ui.selectable_value(&mut draft.comparison, Comparison::Below, "<");
+        threshold_editor(ui, draft);
+
+        ui.label("over");
+        ui.add(
+            egui::DragValue::new(&mut draft.window_secs)
+                .clamp_range(0.0..=crate::MAX_HISTORY_SECS)
+                .suffix(" s"),
+        );
+        ui.label("for");
+        ui.add(
+            egui::DragValue::new(&mut draft.for_secs)
+                .clamp_range(0.0..=3600.0)
+                .suffix(" s"),
+        );
+        ui.label("hysteresis");
+        ui.add(
+            egui::DragValue::new(&mut draft.hysteresis)
+                .clamp_range(0.0..=1.0)
+                .speed(0.01),
+        );
+
+        let valid = !draft.name.is_empty() && engine.rules.iter().all(|x| x.name != draft.name);
+        if ui.add_enabled(valid, egui::Button::new("Add")).clicked() {
+            engine.rules.push(draft.clone());
+            draft.name.clear();
+        }
+    });
+}
@@ -0,0 +1,72 @@
+use crate::correlation::{CorrelationReport, Diagnosis};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CorrelationMetric {
+    Latency,
+    Loss,
+}
+
+fn cell_color(value: f64) -> egui::Color32 {
+    let strength = value.abs().clamp(0.0, 1.0) as f32;
+
+    if value >= 0.0 {
+        egui::Color32::from_rgb(0xB7, 0x48, 0x4D).gamma_multiply(strength)
+    } else {
+        egui::Color32::from_rgb(0x48, 0x7A, 0xB7).gamma_multiply(strength)
+    }
+}
+
+pub fn show_diagnosis(ui: &mut egui::Ui, diagnosis: &Diagnosis) {
+    let color = match diagnosis {
+        Diagnosis::NoData => ui.visuals().weak_text_color(),
+        Diagnosis::Healthy => egui::Color32::from_rgb(0x85, 0xB7, 0x48),
+        Diagnosis::CommonCause(_) => egui::Color32::from_rgb(0xE0, 0x60, 0x40),
+        Diagnosis::SinglePath(_) => egui::Color32::from_rgb(0xE0, 0xB0, 0x40),
+    };
+
+    ui.colored_label(color, diagnosis.describe());
+}
+
+pub fn show(ui: &mut egui::Ui, report: &CorrelationReport, metric: &mut CorrelationMetric) {
+    puffin::profile_function!();
+
+    ui.horizontal(|ui| {
+        ui.label("Metric");
+        ui.selectable_value(metric, CorrelationMetric::Latency, "Latency");
+        ui.selectable_value(metric, CorrelationMetric::Loss, "Loss");
+    });
+
+    let matrix = match metric {
+        CorrelationMetric::Latency => &report.latency,
+        CorrelationMetric::Loss => &report.loss,
+    };
+
+    egui::Grid::new("correlation_matrix")
+        .spacing([4.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("");
+            for target in &report.targets {
+                ui.strong(target);
+            }
+            ui.end_row();
+
+            for (target, row) in report.targets.iter().zip(matrix) {
+                ui.strong(target);
+
+                for value in row {
+                    let (text, fill) = match value {
+                        Some(value) => (format!("{value:.2}"), cell_color(*value)),
+                        None => ("-".to_string(), egui::Color32::TRANSPARENT),
+                    };
+
+                    egui::Frame::none()
+                        .fill(fill)
+                        .inner_margin(egui::Margin::symmetric(8.0, 4.0))
+                        .show(ui, |ui| ui.monospace(text));
+                }
+                ui.end_row();
+            }
+        });
+}
@@ -0,0 +1,89 @@
+use crate::{clock::Clock, events::EventLog, funcs};
+
+use std::io::Write;
+
+// Named after the export time so earlier exports are never overwritten
+fn export_path(clock: &dyn Clock) -> String {
+    let dt = clock.datetime_at(clock.elapsed());
+
+    format!(
+        "ping-events-{:0>4}{:0>2}{:0>2}-{:0>2}{:0>2}{:0>2}.csv",
+        dt.year(),
+        dt.month() as u8,
+        dt.day(),
+        dt.hour(),
+        dt.minute(),
+        dt.second()
+    )
+}
+
+fn export(log: &EventLog) -> String {
+    let path = export_path(log.clock());
+
+    let result = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(log.to_csv().as_bytes()));
+
+    match result {
+        Ok(_) => format!("Saved to {path}"),
+        Err(e) => format!("Failed to save {path}: {e}"),
+    }
+}
+
+pub fn show(
+    ui: &mut egui::Ui,
+    log: &mut EventLog,
+    focus: &mut Option<f64>,
+    export_status: &mut Option<String>,
+) {
+    puffin::profile_function!();
+
+    ui.horizontal(|ui| {
+        ui.strong("Events");
+
+        if ui.button("Export").clicked() {
+            *export_status = Some(export(log));
+        }
+        if ui.button("Copy").clicked() {
+            ui.output_mut(|o| o.copied_text = log.to_csv());
+        }
+        if ui.button("Clear").clicked() {
+            log.clear();
+        }
+
+        if let Some(status) = export_status {
+            ui.weak(status.as_str());
+        }
+    });
+
+    // Only the visible rows are laid out, the log can hold thousands of events
+    let row_height = ui.spacing().interact_size.y;
+
+    egui::ScrollArea::vertical()
+        .auto_shrink([false, false])
+        .show_rows(ui, row_height, log.len(), |ui, rows| {
+            egui::Grid::new("event_log").striped(true).show(ui, |ui| {
+                for event in log.iter().rev().skip(rows.start).take(rows.len()) {
+                    let time = funcs::x_axis_fmt(log.clock(), event.time);
+                    let selected = *focus == Some(event.time);
+
+                    if ui.selectable_label(selected, time).clicked() {
+                        *focus = Some(event.time);
+                    }
+
+                    ui.label(&event.target);
+
+                    if event.kind.is_problem() {
+                        ui.colored_label(egui::Color32::RED, event.kind.label());
+                    } else {
+                        ui.label(event.kind.label());
+                    }
+
+                    ui.label(event.kind.details(&event.target, log.clock()));
+                    ui.end_row();
+                }
+            });
+        });
+}
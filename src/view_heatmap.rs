@@ -0,0 +1,203 @@
+use crate::{clock::Clock, funcs, series::Series, style::TargetStyle};
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+const LOSS_STRIP_HEIGHT: f32 = 6.0;
+const MIN_ROW_HEIGHT: f32 = 48.0;
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeatmapOptions {
+    pub time_buckets: usize,
+    pub latency_buckets: usize,
+}
+
+impl Default for HeatmapOptions {
+    fn default() -> Self {
+        Self {
+            time_buckets: 60,
+            latency_buckets: 20,
+        }
+    }
+}
+
+pub fn show_options(ui: &mut egui::Ui, opts: &mut HeatmapOptions) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Time buckets");
+        ui.add(egui::Slider::new(&mut opts.time_buckets, 10..=240));
+        ui.label("Latency buckets");
+        ui.add(egui::Slider::new(&mut opts.latency_buckets, 5..=100));
+    });
+}
+
+fn loss_color(loss: f64) -> egui::Color32 {
+    let ok = egui::Rgba::from(egui::Color32::from_rgb(0x2E, 0x7D, 0x32));
+    let lost = egui::Rgba::from(egui::Color32::RED);
+    let loss = loss.clamp(0.0, 1.0) as f32;
+
+    (ok * (1.0 - loss) + lost * loss).into()
+}
+
+pub fn show(
+    ui: &mut egui::Ui,
+    timeseries: &HashMap<String, Series>,
+    styles: &HashMap<String, TargetStyle>,
+    time_range: (f64, f64),
+    y_max: f64,
+    opts: &HeatmapOptions,
+    clock: &dyn Clock,
+) {
+    puffin::profile_function!();
+
+    let mut targets: Vec<_> = timeseries
+        .iter()
+        .filter_map(|(target, series)| {
+            let style = styles.get(target).filter(|x| x.visible)?;
+            Some((target, series, style.color))
+        })
+        .collect();
+    targets.sort_by(|a, b| a.0.cmp(b.0));
+
+    if targets.is_empty() {
+        return;
+    }
+
+    let text_color = ui.visuals().text_color();
+    let font = egui::FontId::monospace(10.0);
+    let row_height = (ui.available_height() / targets.len() as f32
+        - ui.spacing().interact_size.y
+        - ui.spacing().item_spacing.y * 2.0)
+        .max(MIN_ROW_HEIGHT);
+
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for (target, series, color) in targets {
+            puffin::profile_scope!("series_iter", target);
+
+            let samples: Vec<(f64, f64)> = series
+                .get_younger_than(time_range.0)
+                .iter()
+                .filter_map(|&x| x)
+                .collect();
+
+            let grid = funcs::heatmap_from_samples(
+                &samples,
+                time_range,
+                opts.time_buckets,
+                y_max,
+                opts.latency_buckets,
+            );
+            let loss = funcs::loss_per_bucket(
+                &samples,
+                &series.timeout_at,
+                time_range,
+                opts.time_buckets,
+            );
+
+            ui.colored_label(color, target);
+
+            let (response, painter) = ui.allocate_painter(
+                egui::vec2(ui.available_width(), row_height),
+                egui::Sense::hover(),
+            );
+
+            let rect = response.rect;
+            let strip_rect = egui::Rect::from_min_max(
+                rect.min,
+                egui::pos2(rect.max.x, rect.min.y + LOSS_STRIP_HEIGHT),
+            );
+            let map_rect =
+                egui::Rect::from_min_max(egui::pos2(rect.min.x, strip_rect.max.y + 1.0), rect.max);
+
+            painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+            let cell_w = map_rect.width() / opts.time_buckets as f32;
+            let cell_h = map_rect.height() / opts.latency_buckets as f32;
+
+            for (t_idx, column) in grid.iter().enumerate() {
+                let x0 = map_rect.min.x + t_idx as f32 * cell_w;
+
+                if let Some(loss) = loss[t_idx] {
+                    painter.rect_filled(
+                        egui::Rect::from_min_max(
+                            egui::pos2(x0, strip_rect.min.y),
+                            egui::pos2(x0 + cell_w, strip_rect.max.y),
+                        ),
+                        0.0,
+                        loss_color(loss),
+                    );
+                }
+
+                let column_max = *column.iter().max().unwrap_or(&0);
+                if column_max == 0 {
+                    continue;
+                }
+
+                for (y_idx, &count) in column.iter().enumerate() {
+                    if count == 0 {
+                        continue;
+                    }
+
+                    // Keep single samples visible next to dense buckets
+                    let intensity = 0.15 + 0.85 * count as f32 / column_max as f32;
+                    let y1 = map_rect.max.y - y_idx as f32 * cell_h;
+
+                    painter.rect_filled(
+                        egui::Rect::from_min_max(
+                            egui::pos2(x0, y1 - cell_h),
+                            egui::pos2(x0 + cell_w, y1),
+                        ),
+                        0.0,
+                        color.gamma_multiply(intensity),
+                    );
+                }
+            }
+
+            painter.text(
+                map_rect.left_top(),
+                egui::Align2::LEFT_TOP,
+                funcs::y_axis_fmt(y_max),
+                font.clone(),
+                text_color,
+            );
+            painter.text(
+                map_rect.left_bottom(),
+                egui::Align2::LEFT_BOTTOM,
+                funcs::x_axis_fmt(clock, time_range.0),
+                font.clone(),
+                text_color,
+            );
+            painter.text(
+                map_rect.right_bottom(),
+                egui::Align2::RIGHT_BOTTOM,
+                funcs::x_axis_fmt(clock, time_range.1),
+                font.clone(),
+                text_color,
+            );
+
+            if let Some(pos) = response.hover_pos() {
+                let t_idx = (((pos.x - map_rect.min.x) / cell_w) as usize)
+                    .min(opts.time_buckets - 1);
+                let y_idx = (((map_rect.max.y - pos.y) / cell_h).max(0.0) as usize)
+                    .min(opts.latency_buckets - 1);
+
+                let t_step = (time_range.1 - time_range.0) / opts.time_buckets as f64;
+                let y_step = y_max / opts.latency_buckets as f64;
+                let loss_text = match loss[t_idx] {
+                    Some(loss) => format!("{:.1}%", loss * 100.0),
+                    None => "-".to_string(),
+                };
+
+                response.on_hover_text_at_pointer(format!(
+                    "Host: {target}\nTime: {} - {}\nLatency: {} - {}\nSamples: {}\nLoss: {loss_text}",
+                    funcs::x_axis_fmt(clock, time_range.0 + t_idx as f64 * t_step),
+                    funcs::x_axis_fmt(clock, time_range.0 + (t_idx + 1) as f64 * t_step),
+                    funcs::y_axis_fmt(y_idx as f64 * y_step),
+                    funcs::y_axis_fmt((y_idx + 1) as f64 * y_step),
+                    grid[t_idx][y_idx],
+                ));
+            }
+        }
+    });
+}
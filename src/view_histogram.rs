@@ -0,0 +1,165 @@
+use crate::{funcs, series::Series, style::TargetStyle};
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BucketScale {
+    Linear,
+    Log,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistogramOptions {
+    pub scale: BucketScale,
+    // In seconds, used for linear buckets
+    pub bucket_width: f64,
+    // Used for log buckets
+    pub buckets_per_decade: u32,
+    pub show_cdf: bool,
+}
+
+impl Default for HistogramOptions {
+    fn default() -> Self {
+        Self {
+            scale: BucketScale::Linear,
+            bucket_width: 0.002,
+            buckets_per_decade: 10,
+            show_cdf: true,
+        }
+    }
+}
+
+impl HistogramOptions {
+    // None for latencies the log axis can't show, like 0 from a clock too coarse to measure
+    fn to_axis(&self, val: f64) -> Option<f64> {
+        match self.scale {
+            BucketScale::Linear => Some(val),
+            BucketScale::Log => (val > 0.0).then(|| val.log10()),
+        }
+    }
+
+    fn from_axis(scale: BucketScale, val: f64) -> f64 {
+        match scale {
+            BucketScale::Linear => val,
+            BucketScale::Log => 10f64.powf(val),
+        }
+    }
+
+    fn axis_bucket_width(&self) -> f64 {
+        match self.scale {
+            BucketScale::Linear => self.bucket_width,
+            BucketScale::Log => 1.0 / self.buckets_per_decade as f64,
+        }
+    }
+}
+
+pub fn show_options(ui: &mut egui::Ui, opts: &mut HistogramOptions) {
+    ui.horizontal_wrapped(|ui| {
+        ui.label("Buckets");
+        ui.selectable_value(&mut opts.scale, BucketScale::Linear, "Linear");
+        ui.selectable_value(&mut opts.scale, BucketScale::Log, "Log");
+
+        match opts.scale {
+            BucketScale::Linear => {
+                let mut width_ms = opts.bucket_width * 1000.0;
+                ui.label("Width");
+                ui.add(
+                    egui::Slider::new(&mut width_ms, 0.1..=100.0)
+                        .logarithmic(true)
+                        .suffix(" ms"),
+                );
+                opts.bucket_width = width_ms / 1000.0;
+            }
+            BucketScale::Log => {
+                ui.label("Per decade");
+                ui.add(egui::Slider::new(&mut opts.buckets_per_decade, 2..=50));
+            }
+        }
+
+        ui.checkbox(&mut opts.show_cdf, "CDF");
+    });
+}
+
+pub fn show(
+    ui: &mut egui::Ui,
+    timeseries: &HashMap<String, Series>,
+    styles: &HashMap<String, TargetStyle>,
+    since: f64,
+    opts: &HistogramOptions,
+) {
+    puffin::profile_function!();
+
+    let scale = opts.scale;
+    let bucket_width = opts.axis_bucket_width();
+
+    egui_plot::Plot::new("histogram")
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .legend(egui_plot::Legend::default())
+        .include_y(0.0)
+        .include_y(1.0)
+        .x_axis_formatter(move |val, _, _| {
+            funcs::y_axis_fmt(HistogramOptions::from_axis(scale, val))
+        })
+        .y_axis_formatter(|val, _, _| format!("{:.0}%", val * 100.0))
+        .x_axis_label("Latency")
+        .y_axis_label("Share")
+        .label_formatter(move |name, point| {
+            let host_label = if !name.is_empty() {
+                format!("Host: {name}\n")
+            } else {
+                "".to_string()
+            };
+
+            format!(
+                "{host_label}Latency: {}\nShare: {:.1}%",
+                funcs::y_axis_fmt(HistogramOptions::from_axis(scale, point.x)),
+                point.y * 100.0
+            )
+        })
+        .show(ui, |plot_ui| {
+            for (target, series) in timeseries.iter() {
+                puffin::profile_scope!("series_iter", target);
+
+                let Some(style) = styles.get(target).filter(|x| x.visible) else {
+                    continue;
+                };
+                let color = style.color;
+                let values: Vec<f64> = series
+                    .get_younger_than(since)
+                    .iter()
+                    .filter_map(|x| x.and_then(|(_, y)| opts.to_axis(y)))
+                    .collect();
+
+                let bars = funcs::histogram_from_values(&values, bucket_width)
+                    .into_iter()
+                    .map(|(start, share)| {
+                        egui_plot::Bar::new(start + bucket_width / 2.0, share).width(bucket_width)
+                    })
+                    .collect();
+
+                plot_ui.bar_chart(egui_plot::BarChart::new(bars).color(color).name(target));
+
+                if opts.show_cdf {
+                    let points: Vec<_> = funcs::cdf_from_values(&values)
+                        .into_iter()
+                        .map(|(x, y)| [x, y])
+                        .collect();
+
+                    plot_ui.line(
+                        egui_plot::Line::new(points)
+                            .color(color)
+                            .width(2.0)
+                            .style(style.dash.line_style())
+                            .name(target),
+                    );
+                }
+            }
+        });
+}
@@ -0,0 +1,216 @@
+use crate::funcs;
+
+use serde::{Deserialize, Serialize};
+
+// Lowest latency representable on the log axis, 10µs
+const LOG_FLOOR: f64 = 1e-5;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum YAxisMode {
+    Max,
+    Log,
+    Percentile,
+    Fixed,
+}
+
+impl YAxisMode {
+    pub const ALL: [YAxisMode; 4] = [Self::Max, Self::Log, Self::Percentile, Self::Fixed];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Max => "Max",
+            Self::Log => "Log",
+            Self::Percentile => "Percentile",
+            Self::Fixed => "Fixed",
+        }
+    }
+
+    // Latency in seconds to the value placed on the plot
+    pub fn project(&self, val: f64) -> f64 {
+        match self {
+            Self::Log => val.max(LOG_FLOOR).log10(),
+            _ => val,
+        }
+    }
+
+    pub fn unproject(&self, val: f64) -> f64 {
+        match self {
+            Self::Log => 10f64.powf(val),
+            _ => val,
+        }
+    }
+
+    // Whether samples can fall outside of the shown range
+    pub fn clips(&self) -> bool {
+        matches!(self, Self::Percentile | Self::Fixed)
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct YAxisOptions {
+    pub mode: YAxisMode,
+    pub percentile: f64,
+    // In seconds
+    pub fixed_min: f64,
+    pub fixed_max: f64,
+}
+
+impl Default for YAxisOptions {
+    fn default() -> Self {
+        Self {
+            mode: YAxisMode::Max,
+            percentile: 95.0,
+            fixed_min: 0.0,
+            fixed_max: 0.1,
+        }
+    }
+}
+
+impl YAxisOptions {
+    // Returns the (min, max) latency to show for the given samples, in seconds
+    pub fn range(&self, values: &[f64], top_padding: f64) -> (f64, f64) {
+        let highest = values.iter().copied().fold(0.0, f64::max);
+
+        match self.mode {
+            YAxisMode::Max => (0.0, highest + top_padding),
+            YAxisMode::Log => {
+                let lowest = values
+                    .iter()
+                    .copied()
+                    .filter(|&x| x > 0.0)
+                    .min_by(|a, b| a.total_cmp(b))
+                    .unwrap_or(LOG_FLOOR);
+
+                // Leave some room below the lowest sample so it doesn't sit on the axis
+                (
+                    (lowest * 0.8).max(LOG_FLOOR),
+                    (highest + top_padding).max(lowest),
+                )
+            }
+            YAxisMode::Percentile => (
+                0.0,
+                funcs::percentile(values, self.percentile) + top_padding,
+            ),
+            YAxisMode::Fixed => (self.fixed_min, self.fixed_max.max(self.fixed_min)),
+        }
+    }
+}
+
+// Grid marks for the log axis at 1, 2 and 5 times every power of ten, the plot values
+// being log10 of seconds
+pub fn log_grid_marks(input: egui_plot::GridInput) -> Vec<egui_plot::GridMark> {
+    let (min, max) = input.bounds;
+    if !(min.is_finite() && max.is_finite()) {
+        return vec![];
+    }
+
+    // Zoomed far out the decade lines are too dense to label anyway
+    let first = min.floor().max(LOG_FLOOR.log10() - 1.0) as i32;
+    let last = max.ceil().min(first as f64 + 30.0) as i32;
+
+    let mut marks = vec![];
+    for decade in first..=last {
+        for (factor, step_size) in [(1.0, 1.0), (2.0, 2f64.log10()), (5.0, 2f64.log10())] {
+            let value = decade as f64 + f64::log10(factor);
+            if (min..=max).contains(&value) {
+                marks.push(egui_plot::GridMark { value, step_size });
+            }
+        }
+    }
+
+    marks
+}
+
+pub fn show_options(ui: &mut egui::Ui, opts: &mut YAxisOptions) {
+    ui.horizontal_wrapped(|ui| {
+        for mode in YAxisMode::ALL {
+            ui.selectable_value(&mut opts.mode, mode, mode.label());
+        }
+
+        match opts.mode {
+            YAxisMode::Percentile => {
+                ui.add(
+                    egui::Slider::new(&mut opts.percentile, 50.0..=99.9)
+                        .text("percentile")
+                        .suffix("%"),
+                );
+            }
+            YAxisMode::Fixed => {
+                ui.add(
+                    egui::DragValue::new(&mut opts.fixed_min)
+                        .speed(0.001)
+                        .clamp_range(0.0..=opts.fixed_max)
+                        .custom_formatter(|val, _| funcs::y_axis_fmt(val))
+                        .prefix("min "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut opts.fixed_max)
+                        .speed(0.001)
+                        .clamp_range(opts.fixed_min..=10.0)
+                        .custom_formatter(|val, _| funcs::y_axis_fmt(val))
+                        .prefix("max "),
+                );
+            }
+            _ => {}
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marks(bounds: (f64, f64)) -> Vec<f64> {
+        log_grid_marks(egui_plot::GridInput {
+            bounds,
+            base_step_size: 0.01,
+        })
+        .iter()
+        .map(|x| YAxisMode::Log.unproject(x.value))
+        .collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < e * 1e-9, "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn log_ticks_at_1_2_5() {
+        let mode = YAxisMode::Log;
+
+        // 1.5ms to 300ms
+        assert_close(
+            &marks((mode.project(0.0015), mode.project(0.3))),
+            &[0.002, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2],
+        );
+        assert!(marks((f64::NEG_INFINITY, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn percentile_range_clips_outliers() {
+        let opts = YAxisOptions {
+            mode: YAxisMode::Percentile,
+            percentile: 90.0,
+            ..Default::default()
+        };
+        let mut values: Vec<f64> = (1..=100).map(|x| x as f64 / 1000.0).collect();
+        values.push(5.0);
+
+        let (min, max) = opts.range(&values, 0.01);
+        assert_eq!(min, 0.0);
+        assert!((0.09..0.105).contains(&max), "{max}");
+
+        // The padding is still added without samples
+        assert_eq!(opts.range(&[], 0.01), (0.0, 0.01));
+    }
+
+    #[test]
+    fn max_range_keeps_outliers() {
+        let values = [0.01, 0.02, 5.0];
+        assert_eq!(YAxisOptions::default().range(&values, 0.01), (0.0, 5.01));
+    }
+}